@@ -0,0 +1,85 @@
+//! Compares the throughput of the framebuffer upscaler at common window
+//! scales, against a simple single-threaded loop.
+//!
+//! Run with `cargo bench --bench upscale`.
+
+use micro_jam_engine::{upscale::upscale, vek::Vec2};
+use std::time::{Duration, Instant};
+
+const W: usize = 200;
+const H: usize = 150;
+
+/// How long to run each case for.
+const DURATION: Duration = Duration::from_secs(1);
+
+/// Fill one output pixel at a time, as the engine used to.
+fn upscale_simple(src: &[u32], dst: &mut [u32], dst_size: Vec2<usize>, scale: usize) {
+    let offset_x = dst_size.x.saturating_sub(W * scale) / 2;
+    let offset_y = dst_size.y.saturating_sub(H * scale) / 2;
+    for j in 0..H {
+        for j2 in 0..scale {
+            let y = offset_y + j * scale + j2;
+            if y >= dst_size.y {
+                break;
+            }
+            for i in 0..W {
+                let x = offset_x + i * scale;
+                if x + scale > dst_size.x {
+                    break;
+                }
+                let idx = y * dst_size.x + x;
+                dst[idx..idx + scale].fill(src[j * W + i]);
+            }
+        }
+    }
+}
+
+/// Run `f` repeatedly, returning the number of frames per second.
+fn measure(mut f: impl FnMut()) -> f64 {
+    // Warm up, including starting rayon's thread pool
+    for _ in 0..10 {
+        f();
+    }
+    let start = Instant::now();
+    let mut frames = 0;
+    while start.elapsed() < DURATION {
+        f();
+        frames += 1;
+    }
+    frames as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let src: Vec<u32> = (0..W * H)
+        .map(|i| (i as u32).wrapping_mul(0x9E37_79B9))
+        .collect();
+
+    println!(
+        "{:>5} {:>11} {:>14} {:>14} {:>8}",
+        "scale", "window", "simple fps", "upscale fps", "speedup"
+    );
+    for scale in [1, 2, 3, 4, 6, 8] {
+        let dst_size = Vec2::new(W * scale, H * scale);
+        let mut expected = vec![0; dst_size.x * dst_size.y];
+        let mut actual = vec![0; dst_size.x * dst_size.y];
+
+        upscale_simple(&src, &mut expected, dst_size, scale);
+        upscale(&src, Vec2::new(W, H), &mut actual, dst_size, scale);
+        assert!(
+            expected == actual,
+            "upscaled images differ at scale {}",
+            scale
+        );
+
+        let simple = measure(|| upscale_simple(&src, &mut expected, dst_size, scale));
+        let parallel = measure(|| upscale(&src, Vec2::new(W, H), &mut actual, dst_size, scale));
+        println!(
+            "{:>5} {:>11} {:>14.0} {:>14.0} {:>7.1}x",
+            scale,
+            format!("{}x{}", dst_size.x, dst_size.y),
+            simple,
+            parallel,
+            parallel / simple
+        );
+    }
+}
@@ -19,7 +19,7 @@ impl Pipeline for Cube {
         color
     }
     fn blend(&self, _: Self::Pixel, color: Self::Fragment) -> Self::Pixel {
-        u32::from_le_bytes((color * 255.0).as_().into_array())
+        Color::from((color * 255.0).as_::<u8>()).to_pixel()
     }
 }
 
@@ -48,13 +48,13 @@ const INDICES: &[usize] = &[
     7, 1, 5, 3, 1, 7, // +z
 ];
 
-struct Color {
+struct SpinningCube {
     time: f32,
     color: Buffer2d<u32>,
     depth: Buffer2d<f32>,
 }
 
-impl Game for Color {
+impl Game for SpinningCube {
     const TITLE: &'static str = "Spinning Cube";
     type SaveData = ();
 
@@ -106,5 +106,5 @@ impl Game for Color {
 }
 
 fn main() {
-    Color::run();
+    SpinningCube::run();
 }
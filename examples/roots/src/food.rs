@@ -1,4 +1,4 @@
-use micro_jam_engine::{prelude::Graphics, vek::*};
+use micro_jam_engine::{prelude::Graphics, vek::*, Rng};
 
 use crate::timer;
 
@@ -25,23 +25,23 @@ impl Food {
         }
     }
 
-    pub fn check_food_timer(&mut self, curr_time: f32, graphics: &Graphics) {
+    pub fn check_food_timer(&mut self, curr_time: f32, graphics: &Graphics, rng: &mut Rng) {
         if self.timer.is_complete(curr_time) {
             self.timer.start_time = curr_time;
-            self.add_food(graphics);
+            self.add_food(graphics, rng);
         }
     }
 
-    pub fn add_food(&mut self, graphics: &Graphics) {
+    pub fn add_food(&mut self, graphics: &Graphics, rng: &mut Rng) {
         let pos = Vec2::new(
-            rand::random::<f32>() * graphics.width() as f32,
-            rand::random::<f32>() * graphics.height() as f32,
+            rng.range(0.0..graphics.width()),
+            rng.range(0.0..graphics.height()),
         );
 
         self.pieces.push(FoodPiece {
             pos,
             // Remaining should be a random number between 20 and 50
-            remaining: rand::random::<f32>() * 30.0 + 20.0,
+            remaining: rng.range(20.0..50.0),
         });
     }
 
@@ -1,6 +1,6 @@
 use food::Food;
 use hecs::World;
-use micro_jam_engine::{prelude::winit::event::VirtualKeyCode, vek::*, Console, Game};
+use micro_jam_engine::{prelude::winit::event::VirtualKeyCode, vek::*, Console, Game, Rng};
 use root::{Root, RootState};
 
 mod food;
@@ -27,13 +27,11 @@ impl Roots {
     /// Each root wants to get further from the player. To do this, the root at
     /// the end will try to move away. Each root between the player and this end
     /// root will then try to move towards the root in front of it.
-    fn move_roots(&mut self) {
+    fn move_roots(&mut self, rng: &mut Rng) {
         for root in self.roots.iter_mut() {
             match root.state {
                 RootState::Exploring { search_point } => {
-                    fn random_search_distance(n: f32) -> f32 {
-                        rand::random::<f32>() * n - n / 2.0
-                    }
+                    let mut random_search_distance = |n: f32| rng.range(-n / 2.0..n / 2.0);
 
                     // If the current search point is None, or we've gotten too
                     // far from the player, create a new one
@@ -126,7 +124,7 @@ impl Roots {
                     // Move the link towards the food with a max speed of 10
                     let dir = (food_pos - *link).try_normalized().unwrap_or(
                         // Pick somewhere random on the screen
-                        Vec2::new(rand::random::<f32>() * 250.0, rand::random::<f32>() * 250.0),
+                        Vec2::new(rng.range(0.0..250.0), rng.range(0.0..250.0)),
                     );
 
                     // Move the link
@@ -236,7 +234,7 @@ impl Game for Roots {
 
         // Add 2 food
         for _ in 0..3 {
-            roots.food.add_food(&console.graphics);
+            roots.food.add_food(&console.graphics, console.rng);
         }
 
         // Debug the screen resolution
@@ -278,13 +276,14 @@ impl Game for Roots {
         // -----
 
         // Check the food timer
-        self.food.check_food_timer(self.time, &console.graphics);
+        self.food
+            .check_food_timer(self.time, &console.graphics, console.rng);
 
         // Remove any food that has been eaten
         self.food.remove_eaten_food();
 
         // Run the roots progression
-        self.move_roots();
+        self.move_roots(console.rng);
 
         // Graphics
         // --------
@@ -0,0 +1,139 @@
+//! Offscreen surfaces to draw onto, and later onto the screen.
+
+use vek::Vec2;
+
+use crate::{
+    graphics::{Graphics, Sprite},
+    Color, Palette,
+};
+
+/// An image of any size that can be drawn onto with the same methods as the
+/// framebuffer, and then drawn onto the screen, or another canvas, with
+/// [`Graphics::draw_canvas`] and [`Graphics::blit`].
+///
+/// Unlike the framebuffer, a canvas keeps track of how opaque each of its
+/// pixels is, and starts out fully transparent. This makes canvases useful
+/// for things that are expensive to draw but rarely change, such as a
+/// minimap or a tiled background, and for building sprites at runtime with
+/// [`Canvas::to_sprite`].
+///
+/// # Example
+///
+/// ```ignore
+/// // In `init`, draw the level's background once
+/// let mut background = Canvas::new(Vec2::new(400, 300));
+/// let mut graphics = background.graphics();
+/// for (pos, tile) in level.tiles() {
+///     graphics.draw_sprite(&TILES, pos * 8, tile);
+/// }
+///
+/// // In `tick`, scroll it with the camera
+/// console.graphics.draw_canvas(&self.background, -self.camera);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    size: Vec2<usize>,
+    pixels: Vec<u32>,
+    alpha: Vec<u8>,
+    palette: Option<Palette>,
+}
+
+impl Canvas {
+    /// Create a transparent canvas of the given size.
+    pub fn new(size: Vec2<usize>) -> Self {
+        Self {
+            size,
+            pixels: vec![0x000000; size.product()],
+            alpha: vec![0; size.product()],
+            palette: None,
+        }
+    }
+
+    pub fn size(&self) -> Vec2<usize> {
+        self.size
+    }
+
+    /// The canvas's own palette, if it's drawn in indexed colour.
+    pub fn palette(&self) -> Option<&Palette> {
+        self.palette.as_ref()
+    }
+
+    /// Start drawing on the canvas. The canvas has its own palette, which can
+    /// be set with [`Graphics::set_palette`] to draw palette indices onto it.
+    pub fn graphics(&mut self) -> Graphics<'_> {
+        Graphics {
+            size: self.size,
+            framebuffer: &mut self.pixels,
+            palette: &mut self.palette,
+            alpha: Some(&mut self.alpha),
+            views: Vec::new(),
+        }
+    }
+
+    /// The colour of a pixel, or `None` if it's outside the canvas. While the
+    /// canvas has a palette, this is the [`Color::index`] drawn there.
+    pub fn get(&self, pos: Vec2<i64>) -> Option<Color> {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.size.x as i64 || pos.y >= self.size.y as i64 {
+            return None;
+        }
+        let index = pos.y as usize * self.size.x + pos.x as usize;
+        Some(Color::from_pixel(self.pixels[index]).with_alpha(self.alpha[index]))
+    }
+
+    /// Copy the canvas into an image, looking up the colours of any palette
+    /// indices.
+    pub fn to_image(&self) -> image::RgbaImage {
+        let mut colors = self.pixels.clone();
+        if let Some(palette) = &self.palette {
+            palette.resolve(&self.pixels, &mut colors);
+        }
+        image::RgbaImage::from_fn(self.size.x as u32, self.size.y as u32, |x, y| {
+            let index = y as usize * self.size.x + x as usize;
+            let color = Color::from_pixel(colors[index]);
+            image::Rgba([color.r, color.g, color.b, self.alpha[index]])
+        })
+    }
+
+    /// Copy the canvas into a sprite, as [`Sprite::from_image`].
+    pub fn to_sprite(&self, center: Vec2<i64>, frames: usize) -> Sprite {
+        Sprite::from_image(self.to_image(), center, frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_transparent() {
+        let canvas = Canvas::new(Vec2::new(2, 2));
+        assert_eq!(canvas.get(Vec2::new(1, 1)), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.get(Vec2::new(2, 0)), None);
+        assert_eq!(canvas.get(Vec2::new(-1, 0)), None);
+    }
+
+    #[test]
+    fn keeps_alpha() {
+        let mut canvas = Canvas::new(Vec2::new(2, 1));
+        canvas
+            .graphics()
+            .draw_pixel(Vec2::new(1, 0), Color::RED.with_alpha(100));
+        assert_eq!(
+            canvas.get(Vec2::new(1, 0)),
+            Some(Color::RED.with_alpha(100))
+        );
+        assert_eq!(canvas.to_image().get_pixel(1, 0).0, [255, 0, 0, 100]);
+
+        canvas.graphics().clear(Color::TRANSPARENT);
+        assert_eq!(canvas.get(Vec2::new(1, 0)), Some(Color::TRANSPARENT));
+    }
+
+    #[test]
+    fn images_look_up_the_palette() {
+        let mut canvas = Canvas::new(Vec2::new(1, 1));
+        let mut graphics = canvas.graphics();
+        graphics.set_palette(Some(Palette::new([Color::BLACK, Color::GREEN])));
+        graphics.clear(Color::index(1));
+        assert_eq!(canvas.to_image().get_pixel(0, 0).0, [0, 255, 0, 255]);
+    }
+}
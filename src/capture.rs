@@ -0,0 +1,217 @@
+//! Capturing what the game looks like, to share outside of it.
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::FilterType,
+    Delay, Frame, ImageOutputFormat, RgbImage,
+};
+use std::{collections::VecDeque, io::Cursor};
+use vek::Vec2;
+
+use crate::Color;
+
+/// How many seconds of gameplay a recording started with the `"record"` key
+/// binding keeps.
+pub(crate) const DEFAULT_RECORDING_SECONDS: f32 = 10.0;
+
+/// Captures requested through the [`crate::Console`], performed by the engine
+/// once the current tick has finished drawing.
+#[derive(Default)]
+pub(crate) struct Capture {
+    /// A screenshot has been requested, and whether it should be upscaled to
+    /// the size of the window.
+    pub(crate) screenshot: Option<bool>,
+    /// The recording in progress, if any.
+    pub(crate) recording: Option<Recording>,
+}
+
+/// The ways that a [`Recording`] can be exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// A looping animated GIF.
+    Gif,
+    /// A numbered sequence of PNG files, one per tick.
+    PngSequence,
+}
+
+/// A ring buffer of the frames drawn over the last few seconds of gameplay.
+pub(crate) struct Recording {
+    format: RecordingFormat,
+    seconds: f32,
+    size: Vec2<usize>,
+    /// Each frame, along with the time it took to reach it.
+    frames: VecDeque<(Vec<u32>, f32)>,
+    duration: f32,
+}
+
+impl Recording {
+    pub(crate) fn new(format: RecordingFormat, seconds: f32, size: Vec2<usize>) -> Self {
+        Self {
+            format,
+            seconds,
+            size,
+            frames: VecDeque::new(),
+            duration: 0.0,
+        }
+    }
+
+    /// Add a frame, forgetting the oldest ones once there are more than the
+    /// recording's length.
+    pub(crate) fn push(&mut self, framebuffer: &[u32], dt: f32) {
+        // Reuse the buffer of a dropped frame, to avoid allocating every tick
+        let mut buffer = None;
+        while self.duration > self.seconds {
+            match self.frames.pop_front() {
+                Some((frame, dt)) => {
+                    self.duration -= dt;
+                    buffer = Some(frame);
+                }
+                None => break,
+            }
+        }
+
+        let mut buffer = buffer.unwrap_or_default();
+        buffer.clear();
+        buffer.extend_from_slice(framebuffer);
+        self.frames.push_back((buffer, dt));
+        self.duration += dt;
+    }
+
+    /// Encode and export the recording. Natively, this happens on a background
+    /// thread so that the game doesn't stall, so any error is reported with
+    /// [`crate::dev_console::report_error`].
+    pub(crate) fn finish(self) {
+        let export = move || {
+            if let Err(err) = self.export() {
+                crate::dev_console::report_error(err);
+            }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(export);
+        #[cfg(target_arch = "wasm32")]
+        export();
+    }
+
+    fn export(self) -> Result<(), String> {
+        let name = format!("recording-{}", crate::unix_time_ms());
+        match self.format {
+            RecordingFormat::Gif => {
+                let mut bytes = Vec::new();
+                self.encode_gif(&mut bytes)
+                    .map_err(|err| format!("Failed to encode {}.gif: {}", name, err))?;
+                export(&format!("{}.gif", name), "image/gif", &bytes)
+            }
+            RecordingFormat::PngSequence => {
+                for (i, (frame, _)) in self.frames.iter().enumerate() {
+                    screenshot_named(&format!("{}-{:04}.png", name, i), frame, self.size, 1)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn encode_gif(&self, bytes: &mut Vec<u8>) -> image::ImageResult<()> {
+        let mut encoder = GifEncoder::new_with_speed(bytes, 10);
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        // GIF delays are in hundredths of a second, and most viewers slow down
+        // frames shorter than 2/100ths. Skip frames until enough time has
+        // passed, tracking the total so that rounding doesn't make the
+        // recording drift out of time.
+        let mut elapsed = 0.0;
+        let mut shown = 0;
+        for (frame, dt) in &self.frames {
+            elapsed += dt;
+            let end = (elapsed * 100.0).round() as u32;
+            let delay = end.saturating_sub(shown);
+            if delay < 2 {
+                continue;
+            }
+            shown = end;
+
+            let img = image::DynamicImage::ImageRgb8(to_image(frame, self.size, 1)).into_rgba8();
+            encoder.encode_frame(Frame::from_parts(
+                img,
+                0,
+                0,
+                Delay::from_numer_denom_ms(delay * 10, 1),
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert a framebuffer into an image, enlarging each pixel by `scale`.
+pub(crate) fn to_image(framebuffer: &[u32], size: Vec2<usize>, scale: usize) -> RgbImage {
+    let img = RgbImage::from_fn(size.x as u32, size.y as u32, |x, y| {
+        let color = Color::from_pixel(framebuffer[y as usize * size.x + x as usize]);
+        image::Rgb([color.r, color.g, color.b])
+    });
+
+    if scale > 1 {
+        image::imageops::resize(
+            &img,
+            (size.x * scale) as u32,
+            (size.y * scale) as u32,
+            FilterType::Nearest,
+        )
+    } else {
+        img
+    }
+}
+
+/// Save a screenshot of the framebuffer as a timestamped PNG.
+pub(crate) fn screenshot(
+    framebuffer: &[u32],
+    size: Vec2<usize>,
+    scale: usize,
+) -> Result<(), String> {
+    let name = format!("screenshot-{}.png", crate::unix_time_ms());
+    screenshot_named(&name, framebuffer, size, scale)
+}
+
+fn screenshot_named(
+    name: &str,
+    framebuffer: &[u32],
+    size: Vec2<usize>,
+    scale: usize,
+) -> Result<(), String> {
+    let mut bytes = Vec::new();
+    to_image(framebuffer, size, scale)
+        .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+        .map_err(|err| format!("Failed to encode {}: {}", name, err))?;
+    export(name, "image/png", &bytes)
+}
+
+/// Write a captured file to the working directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn export(name: &str, _mime: &str, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(name, bytes).map_err(|err| format!("Failed to write {}: {}", name, err))
+}
+
+/// Offer a captured file to the player as a browser download.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn export(name: &str, mime: &str, bytes: &[u8]) -> Result<(), String> {
+    use wasm_bindgen::JsCast;
+
+    let download = || -> Result<(), wasm_bindgen::JsValue> {
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+        let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+            &parts,
+            web_sys::BlobPropertyBag::new().type_(mime),
+        )?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+
+        let document = web_sys::window().unwrap().document().unwrap();
+        let link = document
+            .create_element("a")?
+            .dyn_into::<web_sys::HtmlAnchorElement>()?;
+        link.set_href(&url);
+        link.set_download(name);
+        link.click();
+
+        web_sys::Url::revoke_object_url(&url)
+    };
+
+    download().map_err(|_| format!("Failed to download {}", name))
+}
@@ -0,0 +1,205 @@
+//! Colours, and how they're laid out in the framebuffer.
+
+use serde_derive::{Deserialize, Serialize};
+
+/// A colour to draw with, with an alpha channel for drawing translucently.
+///
+/// Everything that draws takes an `impl Into<Color>`, so colours can also be
+/// given as a `u32` in the framebuffer's `0xRRGGBB` layout, as with
+/// [`Color::hex`]. While drawing in indexed colour, use [`Color::index`] for
+/// palette indices instead.
+///
+/// The framebuffer itself has no alpha channel: translucent colours are
+/// blended with what's already been drawn, and fully transparent ones aren't
+/// drawn at all.
+///
+/// # Example
+///
+/// ```ignore
+/// console.graphics.clear(Color::BLACK);
+/// console.graphics.draw_text("HELLO", Vec2::new(2, 2), Color::hsv(self.hue, 1.0, 1.0));
+/// // Darken the bottom of the screen
+/// console.graphics.draw_rect(Rect::new(0.0, 100.0, 200.0, 50.0), Color::BLACK.with_alpha(128), true);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Self = Self::rgba(0, 0, 0, 0);
+    pub const BLACK: Self = Self::hex(0x000000);
+    pub const WHITE: Self = Self::hex(0xFFFFFF);
+    pub const GRAY: Self = Self::hex(0x808080);
+    pub const RED: Self = Self::hex(0xFF0000);
+    pub const GREEN: Self = Self::hex(0x00FF00);
+    pub const BLUE: Self = Self::hex(0x0000FF);
+    pub const YELLOW: Self = Self::hex(0xFFFF00);
+    pub const CYAN: Self = Self::hex(0x00FFFF);
+    pub const MAGENTA: Self = Self::hex(0xFF00FF);
+    pub const ORANGE: Self = Self::hex(0xFF8000);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::rgba(r, g, b, 255)
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// An opaque colour written as `0xRRGGBB`. Anything above the lowest 24
+    /// bits is ignored.
+    pub const fn hex(hex: u32) -> Self {
+        Self::rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+    }
+
+    /// Parse a colour written as `RRGGBB` or `RRGGBBAA`, optionally starting
+    /// with a `#`.
+    pub fn parse_hex(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.strip_prefix('#').unwrap_or(text);
+        let channel = |i: usize| {
+            text.get(i * 2..i * 2 + 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        };
+        match text.len() {
+            6 => Some(Self::rgb(channel(0)?, channel(1)?, channel(2)?)),
+            8 => Some(Self::rgba(
+                channel(0)?,
+                channel(1)?,
+                channel(2)?,
+                channel(3)?,
+            )),
+            _ => None,
+        }
+    }
+
+    /// An opaque colour from its hue, in degrees, and its saturation and
+    /// value, between 0 and 1.
+    pub fn hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let (saturation, value) = (saturation.clamp(0.0, 1.0), value.clamp(0.0, 1.0));
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        let channel = |c: f32| ((c + m) * 255.0).round() as u8;
+        Self::rgb(channel(r), channel(g), channel(b))
+    }
+
+    /// A palette index, for drawing in indexed colour. See
+    /// [`crate::Palette`].
+    pub const fn index(index: u8) -> Self {
+        Self::rgb(0, 0, index)
+    }
+
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Mix two colours, from all `self` when `t` is 0 to all `other` when
+    /// it's 1. The alpha channel is mixed too.
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        Self::rgba(
+            lerp(self.r, other.r),
+            lerp(self.g, other.g),
+            lerp(self.b, other.b),
+            lerp(self.a, other.a),
+        )
+    }
+
+    /// The colour as a framebuffer pixel, without its alpha channel.
+    pub const fn to_pixel(self) -> u32 {
+        u32::from_le_bytes([self.b, self.g, self.r, 0])
+    }
+
+    /// The opaque colour of a framebuffer pixel.
+    pub const fn from_pixel(pixel: u32) -> Self {
+        let [b, g, r, _] = pixel.to_le_bytes();
+        Self::rgb(r, g, b)
+    }
+}
+
+impl From<u32> for Color {
+    fn from(pixel: u32) -> Self {
+        Self::from_pixel(pixel)
+    }
+}
+
+impl From<Color> for u32 {
+    fn from(color: Color) -> Self {
+        color.to_pixel()
+    }
+}
+
+impl From<vek::Rgb<u8>> for Color {
+    fn from(rgb: vek::Rgb<u8>) -> Self {
+        Self::rgb(rgb.r, rgb.g, rgb.b)
+    }
+}
+
+impl From<vek::Rgba<u8>> for Color {
+    fn from(rgba: vek::Rgba<u8>) -> Self {
+        Self::rgba(rgba.r, rgba.g, rgba.b, rgba.a)
+    }
+}
+
+impl From<image::Rgba<u8>> for Color {
+    fn from(rgba: image::Rgba<u8>) -> Self {
+        let [r, g, b, a] = rgba.0;
+        Self::rgba(r, g, b, a)
+    }
+}
+
+impl From<image::Rgb<u8>> for Color {
+    fn from(rgb: image::Rgb<u8>) -> Self {
+        let [r, g, b] = rgb.0;
+        Self::rgb(r, g, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex() {
+        assert_eq!(Color::parse_hex("#FF8000"), Some(Color::ORANGE));
+        assert_eq!(
+            Color::parse_hex(" 00ff0080 "),
+            Some(Color::GREEN.with_alpha(128))
+        );
+        assert_eq!(Color::parse_hex("#fff"), None);
+        assert_eq!(Color::parse_hex("gg0000"), None);
+        assert_eq!(Color::parse_hex("#ff00é0"), None);
+    }
+
+    #[test]
+    fn hsv() {
+        assert_eq!(Color::hsv(0.0, 1.0, 1.0), Color::RED);
+        assert_eq!(Color::hsv(120.0, 1.0, 1.0), Color::GREEN);
+        assert_eq!(Color::hsv(-120.0, 1.0, 1.0), Color::BLUE);
+        assert_eq!(Color::hsv(60.0, 1.0, 1.0), Color::YELLOW);
+        assert_eq!(Color::hsv(200.0, 0.0, 1.0), Color::WHITE);
+        assert_eq!(Color::hsv(200.0, 1.0, 0.0), Color::BLACK);
+    }
+
+    #[test]
+    fn pixels() {
+        assert_eq!(Color::hex(0x123456).to_pixel(), 0x123456);
+        assert_eq!(Color::from_pixel(0xFF123456), Color::hex(0x123456));
+        assert_eq!(Color::index(7).to_pixel(), 7);
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::Display;
+use vek::{Rect, Vec2};
+
+use crate::{graphics::Graphics, Color, Timing};
+
+/// The number of frames shown in the frame time graph.
+const HISTORY: usize = 100;
+
+const PANEL_COLOR: Color = Color::BLACK;
+const TEXT_COLOR: Color = Color::WHITE;
+const GRAPH_COLOR: Color = Color::GREEN;
+const SLOW_COLOR: Color = Color::RED;
+const TARGET_COLOR: Color = Color::GRAY;
+
+/// The frame time that the graph marks as the target, in seconds.
+const TARGET_FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// A performance overlay, drawn by the engine on top of the game.
+///
+/// It shows the frame rate, a graph of recent frame times, the tick count,
+/// and how long the engine spent running the game's tick, upscaling the
+/// framebuffer and presenting it to the window, all taken from [`Timing`].
+/// Games can add their own values with [`Debug::watch`].
+///
+/// Players can toggle the overlay with the `"debug_overlay"` key binding,
+/// which defaults to F3.
+#[derive(Default)]
+pub struct Debug {
+    visible: bool,
+    watches: Vec<(String, String)>,
+}
+
+impl Debug {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Show a value on the overlay, replacing the last value watched with the
+    /// same name.
+    pub fn watch(&mut self, name: &str, value: impl Display) {
+        let value = value.to_string();
+        match self.watches.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.watches.push((name.to_string(), value)),
+        }
+    }
+
+    /// Stop showing a watched value.
+    pub fn unwatch(&mut self, name: &str) {
+        self.watches.retain(|(n, _)| n != name);
+    }
+
+    pub(crate) fn draw(&self, graphics: &mut Graphics, tick: usize, timing: &Timing) {
+        let ms = |seconds: f32| seconds * 1000.0;
+        let mut lines = vec![
+            format!("FPS {:.0} ({:.1}MS)", timing.fps(), ms(timing.frame_time())),
+            format!("TICK {}", tick),
+            format!(
+                "GAME {:.1} UPSCALE {:.1} BLIT {:.1}MS",
+                ms(timing.tick),
+                ms(timing.upscale),
+                ms(timing.blit)
+            ),
+        ];
+        lines.extend(
+            self.watches
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value)),
+        );
+
+        // The graph shows 1 pixel per millisecond, up to twice the target
+        let graph_h = (ms(TARGET_FRAME_TIME) * 2.0) as i64;
+        let text = lines.join("\n");
+        let text_size = Graphics::text_size(&text);
+        let panel = Rect::new(
+            0.0,
+            0.0,
+            (text_size.x.max(HISTORY as i64) + 4) as f32,
+            (text_size.y + graph_h + 7) as f32,
+        );
+        graphics.draw_rect(panel, PANEL_COLOR, true);
+        graphics.draw_text(&text, Vec2::new(2, 2), TEXT_COLOR);
+
+        let graph_bottom = panel.h as i64 - 3;
+        let target_y = graph_bottom - ms(TARGET_FRAME_TIME) as i64;
+        graphics.draw_line(
+            Vec2::new(2, target_y),
+            Vec2::new(2 + HISTORY as i64 - 1, target_y),
+            TARGET_COLOR,
+        );
+        // Only the most recent frames fit in the graph
+        let skip = timing.frame_times().count().saturating_sub(HISTORY);
+        for (i, dt) in timing.frame_times().skip(skip).enumerate() {
+            let x = 2 + i as i64;
+            let h = (ms(dt) as i64).clamp(1, graph_h);
+            let color = match dt > TARGET_FRAME_TIME * 1.5 {
+                true => SLOW_COLOR,
+                false => GRAPH_COLOR,
+            };
+            graphics.draw_line(
+                Vec2::new(x, graph_bottom - h + 1),
+                Vec2::new(x, graph_bottom),
+                color,
+            );
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::{collections::BTreeMap, str::FromStr, sync::Mutex};
+use vek::{Rect, Vec2};
+use winit::event::VirtualKeyCode;
+
+use crate::{font, graphics::Graphics, Color, Settings};
+
+/// Commands built into the engine, available in every game.
+pub(crate) const BUILTINS: &[(&str, &str)] = &[
+    ("help", "list the available commands"),
+    ("fps", "show the current frame rate"),
+    ("timescale", "get or set the speed of the game"),
+    ("screenshot", "take a screenshot"),
+    ("quit", "quit the game"),
+];
+
+/// The number of lines of output the console remembers.
+const SCROLLBACK: usize = 100;
+
+const PANEL_COLOR: Color = Color::hex(0x101020);
+const BORDER_COLOR: Color = Color::hex(0x404080);
+const INPUT_COLOR: Color = Color::YELLOW;
+const OUTPUT_COLOR: Color = Color::WHITE;
+const ERROR_COLOR: Color = Color::hex(0xFF4040);
+
+type Command<G> = Box<dyn FnMut(&mut G, &Args) -> Result<(), String>>;
+
+/// Commands that can be run from the developer console.
+///
+/// The console drops down from the top of the screen when the `"dev_console"`
+/// key binding, which defaults to the backtick key, is pressed in a debug build
+/// with the `debug_tools` feature. Commands are typed as a name followed by
+/// arguments separated by spaces, and can be tab completed. As well as the
+/// game's commands, there are built-in commands for `help`, `fps`,
+/// `timescale`, `screenshot` and `quit`.
+///
+/// The game's commands can change it outside of its tick, so they're recorded
+/// in replays and run again at the same point when the replay is played back.
+///
+/// # Example
+///
+/// ```ignore
+/// console.commands.register("score", |game: &mut Pong, args| {
+///     game.score = args.get(0)?;
+///     Ok(())
+/// });
+/// ```
+pub struct Commands<G> {
+    commands: BTreeMap<String, Command<G>>,
+}
+
+impl<G> Default for Commands<G> {
+    fn default() -> Self {
+        Self {
+            commands: BTreeMap::new(),
+        }
+    }
+}
+
+impl<G> Commands<G> {
+    /// Register a command, replacing any existing command with the same name.
+    /// If the command fails, the error it returns is shown in the console.
+    pub fn register(
+        &mut self,
+        name: &str,
+        command: impl FnMut(&mut G, &Args) -> Result<(), String> + 'static,
+    ) {
+        self.commands.insert(name.to_string(), Box::new(command));
+    }
+
+    pub fn unregister(&mut self, name: &str) {
+        self.commands.remove(name);
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+
+    pub(crate) fn run(
+        &mut self,
+        game: &mut G,
+        name: &str,
+        args: &Args,
+    ) -> Option<Result<(), String>> {
+        self.commands
+            .get_mut(name)
+            .map(|command| command(game, args))
+    }
+}
+
+/// The arguments given to a command.
+pub struct Args {
+    args: Vec<String>,
+}
+
+impl Args {
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// Parse the argument at `index`, starting at 0, as any type that can be
+    /// parsed from a string.
+    pub fn get<T: FromStr>(&self, index: usize) -> Result<T, String> {
+        let arg = self
+            .args
+            .get(index)
+            .ok_or_else(|| format!("missing argument {}", index + 1))?;
+        arg.parse()
+            .map_err(|_| format!("couldn't understand argument {}: {}", index + 1, arg))
+    }
+
+    /// Like [`Args::get`], but returns `default` if the argument is missing.
+    pub fn get_or<T: FromStr>(&self, index: usize, default: T) -> Result<T, String> {
+        match index < self.args.len() {
+            true => self.get(index),
+            false => Ok(default),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.args.iter().map(String::as_str)
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Errors reported with [`report_error`], waiting to be printed.
+    static ref REPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+}
+
+/// Report an error in the engine rather than in a command. It's written to
+/// stderr, or the browser's console on the web, so it isn't lost in release
+/// builds or before the window opens, and printed to the developer console at
+/// the start of the next frame.
+pub(crate) fn report_error(line: impl Into<String>) {
+    let line = line.into();
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{}", line);
+    #[cfg(target_arch = "wasm32")]
+    web_sys::console::error_1(&line.as_str().into());
+
+    let mut reported = REPORTED.lock().unwrap();
+    // Nothing takes these without the console, so don't let them pile up
+    if reported.len() == SCROLLBACK {
+        reported.remove(0);
+    }
+    reported.push(line);
+}
+
+/// The drop-down console's text entry and output.
+#[derive(Default)]
+pub(crate) struct DevConsole {
+    open: bool,
+    line: String,
+    history: Vec<String>,
+    /// How far back through the history the player has scrolled, if at all.
+    history_pos: Option<usize>,
+    output: Vec<(String, Color)>,
+}
+
+impl DevConsole {
+    pub(crate) fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub(crate) fn print(&mut self, line: impl Into<String>) {
+        self.push_output(line.into(), OUTPUT_COLOR);
+    }
+
+    pub(crate) fn print_error(&mut self, line: impl Into<String>) {
+        self.push_output(line.into(), ERROR_COLOR);
+    }
+
+    /// Print the errors reported with [`report_error`] since the last frame.
+    pub(crate) fn print_reported(&mut self) {
+        let reported = std::mem::take(&mut *REPORTED.lock().unwrap());
+        for line in reported {
+            self.print_error(line);
+        }
+    }
+
+    fn push_output(&mut self, line: String, color: Color) {
+        if self.output.len() == SCROLLBACK {
+            self.output.remove(0);
+        }
+        self.output.push((line, color));
+    }
+
+    /// Handle the player's typing, returning a line once it has been entered.
+    ///
+    /// `names` is every command that can be run, for tab completion.
+    pub(crate) fn handle_input<'a>(
+        &mut self,
+        input: &crate::prelude::Input,
+        settings: &Settings,
+        names: impl Iterator<Item = &'a str>,
+    ) -> Option<String> {
+        // This is a development tool, so players shouldn't stumble on it
+        if !cfg!(all(feature = "debug_tools", debug_assertions)) {
+            return None;
+        }
+
+        let toggle = settings.key("dev_console", VirtualKeyCode::Grave);
+        if input.key_pressed(toggle) || (self.open && input.key_pressed(VirtualKeyCode::Escape)) {
+            self.open = !self.open;
+            return None;
+        }
+        if !self.open {
+            return None;
+        }
+
+        for c in input.text() {
+            match c {
+                '\u{8}' => {
+                    self.line.pop();
+                }
+                // The toggle key also types a character, which shouldn't be
+                // added to the line
+                '`' => {}
+                c if !c.is_control() => self.line.push(c),
+                _ => {}
+            }
+        }
+
+        if input.key_pressed(VirtualKeyCode::Up) && !self.history.is_empty() {
+            let pos = match self.history_pos {
+                Some(pos) => pos.saturating_sub(1),
+                None => self.history.len() - 1,
+            };
+            self.history_pos = Some(pos);
+            self.line = self.history[pos].clone();
+        }
+        if input.key_pressed(VirtualKeyCode::Down) {
+            if let Some(pos) = self.history_pos {
+                match self.history.get(pos + 1) {
+                    Some(line) => {
+                        self.history_pos = Some(pos + 1);
+                        self.line = line.clone();
+                    }
+                    None => {
+                        self.history_pos = None;
+                        self.line.clear();
+                    }
+                }
+            }
+        }
+
+        if input.key_pressed(VirtualKeyCode::Tab) {
+            self.complete(names);
+        }
+
+        if input.key_pressed(VirtualKeyCode::Return) {
+            let line = std::mem::take(&mut self.line);
+            self.history_pos = None;
+            if line.trim().is_empty() {
+                return None;
+            }
+            self.push_output(format!("> {}", line), INPUT_COLOR);
+            if self.history.last() != Some(&line) {
+                self.history.push(line.clone());
+            }
+            return Some(line);
+        }
+
+        None
+    }
+
+    /// Complete the command name being typed, as far as it can be completed
+    /// without ambiguity. If there are several options, they are listed.
+    fn complete<'a>(&mut self, names: impl Iterator<Item = &'a str>) {
+        if self.line.contains(' ') {
+            return;
+        }
+
+        let matches: Vec<&str> = names.filter(|name| name.starts_with(&self.line)).collect();
+        match matches[..] {
+            [] => {}
+            [name] => self.line = format!("{} ", name),
+            _ => {
+                let mut prefix = matches[0].to_string();
+                for name in &matches[1..] {
+                    while !name.starts_with(&prefix) {
+                        prefix.pop();
+                    }
+                }
+                self.line = prefix;
+                self.print(matches.join(" "));
+            }
+        }
+    }
+
+    pub(crate) fn draw(&self, graphics: &mut Graphics) {
+        let h = graphics.size.y as i64 / 2;
+        let w = graphics.size.x as i64;
+        graphics.draw_rect(Rect::new(0, 0, w, h).as_(), PANEL_COLOR, true);
+        graphics.draw_line(Vec2::new(0, h), Vec2::new(w - 1, h), BORDER_COLOR);
+
+        let prompt_y = h - font::ADVANCE_Y;
+        let prompt = format!("> {}_", self.line);
+        // Keep the end of a long line in view
+        let max_chars = (w - 4) / font::ADVANCE_X;
+        let skip = (prompt.chars().count() as i64 - max_chars).max(0) as usize;
+        let prompt: String = prompt.chars().skip(skip).collect();
+        graphics.draw_text(&prompt, Vec2::new(2, prompt_y), INPUT_COLOR);
+
+        let mut y = prompt_y - font::ADVANCE_Y;
+        for (line, color) in self.output.iter().rev() {
+            if y < 0 {
+                break;
+            }
+            graphics.draw_text(line, Vec2::new(2, y), *color);
+            y -= font::ADVANCE_Y;
+        }
+    }
+}
+
+/// Split a line into a command name and its arguments.
+pub(crate) fn parse(line: &str) -> Option<(&str, Args)> {
+    let mut words = line.split_whitespace();
+    let name = words.next()?;
+    Some((
+        name,
+        Args {
+            args: words.map(str::to_string).collect(),
+        },
+    ))
+}
@@ -0,0 +1,321 @@
+//! Post-processing the game as it's upscaled, to imitate an old CRT screen.
+
+use vek::Vec2;
+
+use crate::Color;
+
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
+
+/// How far the bloom spreads, in game pixels.
+const BLOOM_RADIUS: isize = 2;
+
+/// A colour with red, green and blue channels between 0 and 1.
+type Rgb = [f32; 3];
+
+/// The pattern of phosphors that make up the screen, for [`Filters::mask`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mask {
+    /// Vertical red, green and blue stripes.
+    ApertureGrille,
+    /// Red, green and blue dots, staggered every other pair of rows.
+    ShadowMask,
+}
+
+/// Effects applied to the game as it's upscaled to fill the window, such as
+/// scanlines and screen curvature.
+///
+/// Filters are set up with [`crate::Game::FILTERS`], and can be changed while
+/// the game is running with [`crate::Console::filters`]. They're drawn in
+/// software, so they work on the web too, but they're much slower than a plain
+/// upscale. Players can turn them off with the `"filters"` key binding, which
+/// defaults to F9.
+///
+/// Each effect's strength is between 0, which turns it off, and 1, unless
+/// stated otherwise. Filters don't affect screenshots or recordings.
+///
+/// # Example
+///
+/// ```ignore
+/// const FILTERS: Filters = Filters {
+///     curvature: 0.0,
+///     ..Filters::CRT
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Filters {
+    /// How dark the gaps between rows of game pixels are. These only show up
+    /// when the game is upscaled at least twice.
+    pub scanlines: f32,
+    /// The phosphor pattern laid over the screen, and how strongly it shows.
+    pub mask: Option<(Mask, f32)>,
+    /// How much the screen bulges outwards, as if it were the glass of a
+    /// tube. Anything curved off the edge of the screen is cut off.
+    pub curvature: f32,
+    /// How much bright colours glow into their surroundings.
+    pub bloom: f32,
+    /// How far the red and blue channels are split apart at the left and
+    /// right edges of the screen, in game pixels.
+    pub chromatic_aberration: f32,
+    /// How much the corners of the screen are darkened.
+    pub vignette: f32,
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl Filters {
+    /// No filters, for a plain pixel-perfect upscale.
+    pub const NONE: Self = Self {
+        scanlines: 0.0,
+        mask: None,
+        curvature: 0.0,
+        bloom: 0.0,
+        chromatic_aberration: 0.0,
+        vignette: 0.0,
+    };
+
+    /// A typical CRT look, with a bit of everything.
+    pub const CRT: Self = Self {
+        scanlines: 0.5,
+        mask: Some((Mask::ApertureGrille, 0.25)),
+        curvature: 0.3,
+        bloom: 0.4,
+        chromatic_aberration: 0.5,
+        vignette: 0.4,
+    };
+
+    /// Whether any of the filters are turned on.
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::NONE
+    }
+
+    /// Like [`crate::upscale::upscale`], but with the filters applied. Every
+    /// pixel of `dst` is drawn, with those outside the screen left black.
+    pub fn upscale(
+        &self,
+        src: &[u32],
+        src_size: Vec2<usize>,
+        dst: &mut [u32],
+        dst_size: Vec2<usize>,
+        scale: usize,
+    ) {
+        // There's nothing to draw while the window is minimised
+        if dst_size.x == 0 || dst_size.y == 0 || src_size.x == 0 || src_size.y == 0 {
+            return;
+        }
+        let scale = scale.max(1);
+        let image = src_size * scale;
+        let offset = Vec2::new(
+            dst_size.x.saturating_sub(image.x) / 2,
+            dst_size.y.saturating_sub(image.y) / 2,
+        );
+        let colors: Vec<Rgb> = src.iter().map(|pixel| unpack(*pixel)).collect();
+        let glow = match self.bloom > 0.0 {
+            true => Some(glow(&colors, src_size)),
+            false => None,
+        };
+
+        let filter_row = |(y, row): (usize, &mut [u32])| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                // Where the pixel is on the screen, from 0 to 1 in both axes
+                let uv =
+                    (Vec2::new(x, y).as_::<f32>() - offset.as_::<f32>() + 0.5) / image.as_::<f32>();
+                *pixel = self.shade(&colors, src_size, glow.as_deref(), uv, Vec2::new(x, y));
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        dst.par_chunks_mut(dst_size.x)
+            .enumerate()
+            .for_each(filter_row);
+
+        // Threads aren't available on the web
+        #[cfg(target_arch = "wasm32")]
+        dst.chunks_mut(dst_size.x).enumerate().for_each(filter_row);
+    }
+
+    /// The colour of a pixel of the window, at `uv` on the screen and `pos`
+    /// in the window.
+    fn shade(
+        &self,
+        colors: &[Rgb],
+        src_size: Vec2<usize>,
+        glow: Option<&[Rgb]>,
+        uv: Vec2<f32>,
+        pos: Vec2<usize>,
+    ) -> u32 {
+        // Bulge the screen out from its centre
+        let mut centred = uv * 2.0 - 1.0;
+        if self.curvature > 0.0 {
+            let bulge = self.curvature * 0.25;
+            centred = Vec2::new(
+                centred.x * (1.0 + bulge * centred.y * centred.y),
+                centred.y * (1.0 + bulge * centred.x * centred.x),
+            );
+        }
+        let uv = centred * 0.5 + 0.5;
+        if uv.x < 0.0 || uv.y < 0.0 || uv.x >= 1.0 || uv.y >= 1.0 {
+            return 0x000000;
+        }
+
+        // Sample the game, splitting the red and blue channels apart
+        let sample_pos = uv * src_size.as_::<f32>();
+        let row = &colors[sample_pos.y as usize * src_size.x..][..src_size.x];
+        let mut color = match self.chromatic_aberration > 0.0 {
+            true => {
+                let split = self.chromatic_aberration * centred.x;
+                let channel = |dx: f32, channel: usize| {
+                    let x = sample_pos.x + dx;
+                    match x >= 0.0 && (x as usize) < src_size.x {
+                        true => row[x as usize][channel],
+                        false => 0.0,
+                    }
+                };
+                [channel(split, 0), channel(0.0, 1), channel(-split, 2)]
+            }
+            false => row[sample_pos.x as usize],
+        };
+
+        if let Some(glow) = glow {
+            let glow = sample(glow, src_size, sample_pos);
+            for (c, g) in color.iter_mut().zip(glow) {
+                *c += g * self.bloom;
+            }
+        }
+
+        let mut brightness = 1.0;
+
+        // Darken the bottom of each row of game pixels
+        if self.scanlines > 0.0 {
+            let gap = (sample_pos.y.fract() * 2.0 - 1.0).max(0.0).sqrt();
+            brightness -= self.scanlines * gap;
+        }
+
+        // Darken towards the corners
+        if self.vignette > 0.0 {
+            let edge = (uv.x * (1.0 - uv.x) * uv.y * (1.0 - uv.y) * 16.0)
+                .sqrt()
+                .sqrt();
+            brightness *= 1.0 - self.vignette * (1.0 - edge);
+        }
+
+        // Only let through the channel of the phosphor under the pixel
+        let phosphor = match self.mask {
+            Some((Mask::ApertureGrille, strength)) => Some((pos.x % 3, strength)),
+            Some((Mask::ShadowMask, strength)) => Some(((pos.x + pos.y / 2 % 2 * 2) % 3, strength)),
+            None => None,
+        };
+        for (i, c) in color.iter_mut().enumerate() {
+            *c *= brightness;
+            if let Some((phosphor, strength)) = phosphor {
+                if i != phosphor {
+                    *c *= 1.0 - strength;
+                }
+            }
+        }
+
+        pack(color)
+    }
+}
+
+fn unpack(pixel: u32) -> Rgb {
+    let color = Color::from_pixel(pixel);
+    [color.r, color.g, color.b].map(|c| c as f32 / 255.0)
+}
+
+fn pack(rgb: Rgb) -> u32 {
+    let [r, g, b] = rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Color::rgb(r, g, b).to_pixel()
+}
+
+/// A blurred copy of the game for the bloom to be taken from, with bright
+/// colours weighted much more than dark ones.
+fn glow(colors: &[Rgb], size: Vec2<usize>) -> Vec<Rgb> {
+    let bright: Vec<Rgb> = colors.iter().map(|color| color.map(|c| c * c)).collect();
+    let blurred = blur(&bright, size, Vec2::new(1, 0));
+    blur(&blurred, size, Vec2::new(0, 1))
+}
+
+/// Blur an image along one direction by averaging each pixel with its
+/// neighbours.
+fn blur(src: &[Rgb], size: Vec2<usize>, direction: Vec2<isize>) -> Vec<Rgb> {
+    let mut dst = vec![[0.0; 3]; src.len()];
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let mut sum = [0.0; 3];
+            for d in -BLOOM_RADIUS..=BLOOM_RADIUS {
+                let p = Vec2::new(x, y).as_::<isize>() + direction * d;
+                if p.x >= 0 && p.y >= 0 && (p.x as usize) < size.x && (p.y as usize) < size.y {
+                    let color = src[p.y as usize * size.x + p.x as usize];
+                    for (s, c) in sum.iter_mut().zip(color) {
+                        *s += c;
+                    }
+                }
+            }
+            dst[y * size.x + x] = sum.map(|s| s / (BLOOM_RADIUS * 2 + 1) as f32);
+        }
+    }
+    dst
+}
+
+/// Sample an image at a position in pixels, blending between the nearest
+/// four pixels.
+fn sample(image: &[Rgb], size: Vec2<usize>, pos: Vec2<f32>) -> Rgb {
+    let pos = pos - 0.5;
+    let (fx, fy) = (pos.x - pos.x.floor(), pos.y - pos.y.floor());
+    let at = |dx: f32, dy: f32| {
+        let x = ((pos.x.floor() + dx).max(0.0) as usize).min(size.x - 1);
+        let y = ((pos.y.floor() + dy).max(0.0) as usize).min(size.y - 1);
+        image[y * size.x + x]
+    };
+    let (a, b, c, d) = (at(0.0, 0.0), at(1.0, 0.0), at(0.0, 1.0), at(1.0, 1.0));
+    let mut color = [0.0; 3];
+    for i in 0..3 {
+        let top = a[i] + (b[i] - a[i]) * fx;
+        let bottom = c[i] + (d[i] - c[i]) * fx;
+        color[i] = top + (bottom - top) * fy;
+    }
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_windows() {
+        let src = [0xFFFFFF; 4];
+        for size in [Vec2::new(0, 0), Vec2::new(0, 4), Vec2::new(4, 0)] {
+            let mut dst = vec![0; size.product()];
+            Filters::CRT.upscale(&src, Vec2::new(2, 2), &mut dst, size, 2);
+        }
+        Filters::CRT.upscale(&[], Vec2::new(0, 0), &mut [0; 4], Vec2::new(2, 2), 2);
+    }
+
+    #[test]
+    fn no_filters_matches_nearest() {
+        let src = [0x102030, 0x405060, 0x708090, 0xA0B0C0];
+        let mut filtered = vec![0; 6 * 4];
+        Filters::NONE.upscale(&src, Vec2::new(2, 2), &mut filtered, Vec2::new(6, 4), 2);
+        let mut nearest = vec![0; 6 * 4];
+        crate::upscale::upscale(&src, Vec2::new(2, 2), &mut nearest, Vec2::new(6, 4), 2);
+        assert_eq!(filtered, nearest);
+    }
+
+    #[test]
+    fn curvature_blacks_out_the_corners() {
+        let src = [0xFFFFFF; 8 * 8];
+        let mut dst = vec![0; 32 * 32];
+        let filters = Filters {
+            curvature: 1.0,
+            ..Filters::NONE
+        };
+        filters.upscale(&src, Vec2::new(8, 8), &mut dst, Vec2::new(32, 32), 4);
+        assert_eq!(dst[0], 0x000000);
+        assert_eq!(dst[16 * 32 + 16], 0xFFFFFF);
+    }
+}
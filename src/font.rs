@@ -0,0 +1,119 @@
+//! A tiny built-in bitmap font, used by [`crate::prelude::Graphics::draw_text`].
+
+/// The size of a glyph, in pixels.
+pub const GLYPH_W: i64 = 3;
+pub const GLYPH_H: i64 = 5;
+
+/// The distance between the starts of neighbouring glyphs and lines.
+pub const ADVANCE_X: i64 = GLYPH_W + 1;
+pub const ADVANCE_Y: i64 = GLYPH_H + 2;
+
+/// Get the rows of a glyph, top to bottom, with the leftmost pixel in the
+/// highest bit. Lowercase letters are drawn as uppercase, and characters
+/// without a glyph are drawn as `?`.
+pub fn glyph(c: char) -> [u8; 5] {
+    match c {
+        ' '..='~' => GLYPHS[c as usize - ' ' as usize],
+        _ => GLYPHS['?' as usize - ' ' as usize],
+    }
+}
+
+#[rustfmt::skip]
+const GLYPHS: [[u8; 5]; 95] = [
+    [0b000, 0b000, 0b000, 0b000, 0b000], // ' '
+    [0b010, 0b010, 0b010, 0b000, 0b010], // !
+    [0b101, 0b101, 0b000, 0b000, 0b000], // "
+    [0b101, 0b111, 0b101, 0b111, 0b101], // #
+    [0b011, 0b110, 0b010, 0b011, 0b110], // $
+    [0b101, 0b001, 0b010, 0b100, 0b101], // %
+    [0b010, 0b101, 0b010, 0b101, 0b011], // &
+    [0b010, 0b010, 0b000, 0b000, 0b000], // '
+    [0b001, 0b010, 0b010, 0b010, 0b001], // (
+    [0b100, 0b010, 0b010, 0b010, 0b100], // )
+    [0b000, 0b101, 0b010, 0b101, 0b000], // *
+    [0b000, 0b010, 0b111, 0b010, 0b000], // +
+    [0b000, 0b000, 0b000, 0b010, 0b100], // ,
+    [0b000, 0b000, 0b111, 0b000, 0b000], // -
+    [0b000, 0b000, 0b000, 0b000, 0b010], // .
+    [0b001, 0b001, 0b010, 0b100, 0b100], // /
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b001, 0b001, 0b001], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+    [0b000, 0b010, 0b000, 0b010, 0b000], // :
+    [0b000, 0b010, 0b000, 0b010, 0b100], // ;
+    [0b001, 0b010, 0b100, 0b010, 0b001], // <
+    [0b000, 0b111, 0b000, 0b111, 0b000], // =
+    [0b100, 0b010, 0b001, 0b010, 0b100], // >
+    [0b111, 0b001, 0b011, 0b000, 0b010], // ?
+    [0b010, 0b101, 0b111, 0b100, 0b011], // @
+    [0b010, 0b101, 0b111, 0b101, 0b101], // A
+    [0b110, 0b101, 0b110, 0b101, 0b110], // B
+    [0b011, 0b100, 0b100, 0b100, 0b011], // C
+    [0b110, 0b101, 0b101, 0b101, 0b110], // D
+    [0b111, 0b100, 0b110, 0b100, 0b111], // E
+    [0b111, 0b100, 0b110, 0b100, 0b100], // F
+    [0b011, 0b100, 0b101, 0b101, 0b011], // G
+    [0b101, 0b101, 0b111, 0b101, 0b101], // H
+    [0b111, 0b010, 0b010, 0b010, 0b111], // I
+    [0b001, 0b001, 0b001, 0b101, 0b010], // J
+    [0b101, 0b101, 0b110, 0b101, 0b101], // K
+    [0b100, 0b100, 0b100, 0b100, 0b111], // L
+    [0b101, 0b111, 0b111, 0b101, 0b101], // M
+    [0b110, 0b101, 0b101, 0b101, 0b101], // N
+    [0b010, 0b101, 0b101, 0b101, 0b010], // O
+    [0b110, 0b101, 0b110, 0b100, 0b100], // P
+    [0b010, 0b101, 0b101, 0b110, 0b011], // Q
+    [0b110, 0b101, 0b110, 0b101, 0b101], // R
+    [0b011, 0b100, 0b010, 0b001, 0b110], // S
+    [0b111, 0b010, 0b010, 0b010, 0b010], // T
+    [0b101, 0b101, 0b101, 0b101, 0b111], // U
+    [0b101, 0b101, 0b101, 0b101, 0b010], // V
+    [0b101, 0b101, 0b111, 0b111, 0b101], // W
+    [0b101, 0b101, 0b010, 0b101, 0b101], // X
+    [0b101, 0b101, 0b010, 0b010, 0b010], // Y
+    [0b111, 0b001, 0b010, 0b100, 0b111], // Z
+    [0b011, 0b010, 0b010, 0b010, 0b011], // [
+    [0b100, 0b100, 0b010, 0b001, 0b001], // \
+    [0b110, 0b010, 0b010, 0b010, 0b110], // ]
+    [0b010, 0b101, 0b000, 0b000, 0b000], // ^
+    [0b000, 0b000, 0b000, 0b000, 0b111], // _
+    [0b100, 0b010, 0b000, 0b000, 0b000], // `
+    // Lowercase letters share the uppercase glyphs
+    [0b010, 0b101, 0b111, 0b101, 0b101], // a
+    [0b110, 0b101, 0b110, 0b101, 0b110], // b
+    [0b011, 0b100, 0b100, 0b100, 0b011], // c
+    [0b110, 0b101, 0b101, 0b101, 0b110], // d
+    [0b111, 0b100, 0b110, 0b100, 0b111], // e
+    [0b111, 0b100, 0b110, 0b100, 0b100], // f
+    [0b011, 0b100, 0b101, 0b101, 0b011], // g
+    [0b101, 0b101, 0b111, 0b101, 0b101], // h
+    [0b111, 0b010, 0b010, 0b010, 0b111], // i
+    [0b001, 0b001, 0b001, 0b101, 0b010], // j
+    [0b101, 0b101, 0b110, 0b101, 0b101], // k
+    [0b100, 0b100, 0b100, 0b100, 0b111], // l
+    [0b101, 0b111, 0b111, 0b101, 0b101], // m
+    [0b110, 0b101, 0b101, 0b101, 0b101], // n
+    [0b010, 0b101, 0b101, 0b101, 0b010], // o
+    [0b110, 0b101, 0b110, 0b100, 0b100], // p
+    [0b010, 0b101, 0b101, 0b110, 0b011], // q
+    [0b110, 0b101, 0b110, 0b101, 0b101], // r
+    [0b011, 0b100, 0b010, 0b001, 0b110], // s
+    [0b111, 0b010, 0b010, 0b010, 0b010], // t
+    [0b101, 0b101, 0b101, 0b101, 0b111], // u
+    [0b101, 0b101, 0b101, 0b101, 0b010], // v
+    [0b101, 0b101, 0b111, 0b111, 0b101], // w
+    [0b101, 0b101, 0b010, 0b101, 0b101], // x
+    [0b101, 0b101, 0b010, 0b010, 0b010], // y
+    [0b111, 0b001, 0b010, 0b100, 0b111], // z
+    [0b011, 0b010, 0b110, 0b010, 0b011], // {
+    [0b010, 0b010, 0b010, 0b010, 0b010], // |
+    [0b110, 0b010, 0b011, 0b010, 0b110], // }
+    [0b000, 0b011, 0b110, 0b000, 0b000], // ~
+];
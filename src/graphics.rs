@@ -1,9 +1,33 @@
 use line_drawing::Bresenham;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock},
+    time::SystemTime,
+};
 use vek::{Rect, Vec2};
 
+use crate::{font, Canvas, Color, Palette};
+
 pub struct Graphics<'tick> {
     pub size: Vec2<usize>,
     pub framebuffer: &'tick mut [u32],
+    pub(crate) palette: &'tick mut Option<Palette>,
+    /// How opaque each pixel is, when drawing to a [`Canvas`]. The framebuffer
+    /// itself is always opaque.
+    pub(crate) alpha: Option<&'tick mut [u8]>,
+    /// The clip rectangles and offsets pushed so far, each combined with the
+    /// ones before it.
+    pub(crate) views: Vec<View>,
+}
+
+/// Where drawing ends up, in framebuffer pixels.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct View {
+    /// The area that can be drawn to.
+    clip: Rect<i64, i64>,
+    /// How far everything drawn is moved.
+    offset: Vec2<i64>,
 }
 
 impl<'tick> Graphics<'tick> {
@@ -15,26 +39,174 @@ impl<'tick> Graphics<'tick> {
         self.size.y as f32
     }
 
+    /// The palette that the framebuffer's pixels index into, if the game is
+    /// drawing in indexed colour. See [`Palette`].
+    pub fn palette(&self) -> Option<&Palette> {
+        self.palette.as_ref()
+    }
+
+    pub fn palette_mut(&mut self) -> Option<&mut Palette> {
+        self.palette.as_mut()
+    }
+
+    /// Start drawing palette indices rather than colours, or go back to
+    /// colours with `None`. The framebuffer isn't converted, so it should be
+    /// redrawn in full afterwards.
+    pub fn set_palette(&mut self, palette: Option<Palette>) {
+        *self.palette = palette;
+    }
+
+    /// Stop drawing outside of a rectangle, until the matching
+    /// [`Graphics::pop`]. The rectangle is moved by the current offset, and
+    /// anything outside an earlier clip rectangle stays clipped.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // Scroll the inventory's contents without them spilling out of it
+    /// console.graphics.push_clip(Rect::new(10, 10, 80, 60));
+    /// console.graphics.push_offset(Vec2::new(10, 10 - self.scroll));
+    /// for (i, item) in self.items.iter().enumerate() {
+    ///     console.graphics.draw_text(&item.name, Vec2::new(2, i as i64 * 8), Color::WHITE);
+    /// }
+    /// console.graphics.pop();
+    /// console.graphics.pop();
+    /// ```
+    pub fn push_clip(&mut self, rect: Rect<i64, i64>) {
+        let view = self.view();
+        let (x, y) = (rect.x + view.offset.x, rect.y + view.offset.y);
+        let left = x.max(view.clip.x);
+        let top = y.max(view.clip.y);
+        let right = (x + rect.w).min(view.clip.x + view.clip.w);
+        let bottom = (y + rect.h).min(view.clip.y + view.clip.h);
+        let clip = match left < right && top < bottom {
+            true => Rect::new(left, top, right - left, bottom - top),
+            // Keep empty clips inside the framebuffer, so they can't be used
+            // to index past its end
+            false => Rect::new(0, 0, 0, 0),
+        };
+        self.views.push(View { clip, ..view });
+    }
+
+    /// Move everything drawn by `offset`, on top of the current offset, until
+    /// the matching [`Graphics::pop`]. This is useful for drawing a camera's
+    /// view, or a panel's contents relative to its corner.
+    pub fn push_offset(&mut self, offset: Vec2<i64>) {
+        let view = self.view();
+        self.views.push(View {
+            offset: view.offset + offset,
+            ..view
+        });
+    }
+
+    /// Undo the last [`Graphics::push_clip`] or [`Graphics::push_offset`].
+    /// Everything that's been pushed is undone at the end of each tick.
+    pub fn pop(&mut self) {
+        self.views.pop();
+    }
+
+    /// The current clip rectangle and offset.
+    fn view(&self) -> View {
+        self.views.last().copied().unwrap_or(View {
+            clip: Rect::new(0, 0, self.size.x as i64, self.size.y as i64),
+            offset: Vec2::zero(),
+        })
+    }
+
+    /// The area that can be drawn to, in the same coordinates as drawing, so
+    /// with the offset taken away from the clip rectangle.
+    pub(crate) fn drawable(&self) -> Rect<i64, i64> {
+        let View { clip, offset } = self.view();
+        Rect::new(clip.x - offset.x, clip.y - offset.y, clip.w, clip.h)
+    }
+
+    /// Where a pixel drawn at `pos` ends up in the framebuffer, or `None` if
+    /// it's outside the clip rectangle.
+    fn index(&self, pos: Vec2<i64>) -> Option<usize> {
+        let View { clip, offset } = self.view();
+        let pos = pos + offset;
+        if pos.x < clip.x || pos.y < clip.y || pos.x >= clip.x + clip.w || pos.y >= clip.y + clip.h
+        {
+            return None;
+        }
+        Some(pos.y as usize * self.size.x + pos.x as usize)
+    }
+
+    /// The colour of the pixel drawn at `pos`, including its alpha on a
+    /// [`Canvas`], or `None` if it's outside the clip rectangle.
+    pub(crate) fn get(&self, pos: Vec2<i64>) -> Option<Color> {
+        let index = self.index(pos)?;
+        let alpha = match &self.alpha {
+            Some(alpha) => alpha[index],
+            None => 255,
+        };
+        Some(Color::from_pixel(self.framebuffer[index]).with_alpha(alpha))
+    }
+
     // TODO: Methods for drawing shapes, sprites, perhaps even triangles, as
     // well as getting access to the framebuffer
-    pub fn clear(&mut self, color: u32) {
-        for pixel in self.framebuffer.iter_mut() {
-            *pixel = color;
+    /// Fill the framebuffer with a colour, or just the clip rectangle if one
+    /// has been pushed. This replaces what's there rather than blending with
+    /// it, so it can also clear a [`Canvas`] back to transparent.
+    pub fn clear(&mut self, color: impl Into<Color>) {
+        let (color, clip) = (color.into(), self.view().clip);
+        if clip.w == 0 || clip.h == 0 {
+            return;
+        }
+        for y in clip.y..clip.y + clip.h {
+            let start = y as usize * self.size.x + clip.x as usize;
+            let row = start..start + clip.w as usize;
+            self.framebuffer[row.clone()].fill(color.to_pixel());
+            if let Some(alpha) = &mut self.alpha {
+                alpha[row].fill(color.a);
+            }
+        }
+    }
+
+    /// Draw a single pixel. Pixels outside the framebuffer, or the clip
+    /// rectangle, are skipped.
+    pub fn draw_pixel(&mut self, pos: Vec2<i64>, color: impl Into<Color>) {
+        self.blend_pixel(pos, color.into(), BlendMode::Alpha);
+    }
+
+    /// Draw a single pixel with the given blend mode.
+    pub(crate) fn blend_pixel(&mut self, pos: Vec2<i64>, color: Color, mode: BlendMode) {
+        let index = match self.index(pos) {
+            Some(index) => index,
+            None => return,
+        };
+        let pixel = &mut self.framebuffer[index];
+
+        // Palette indices can't be blended
+        if self.palette.is_some() {
+            if color.a > 0 || mode == BlendMode::Replace {
+                *pixel = color.to_pixel();
+                if let Some(alpha) = &mut self.alpha {
+                    alpha[index] = color.a;
+                }
+            }
+            return;
+        }
+
+        match &mut self.alpha {
+            Some(alpha) => {
+                let blended = mode.blend(Color::from_pixel(*pixel).with_alpha(alpha[index]), color);
+                *pixel = blended.to_pixel();
+                alpha[index] = blended.a;
+            }
+            None => *pixel = mode.blend(Color::from_pixel(*pixel), color).to_pixel(),
         }
     }
 
     /// Draw a rectangle. This takes a starting position and a size, and fills
     /// the rectangle with the given color.
-    pub fn draw_rect(&mut self, rect: Rect<f32, f32>, color: u32, filled: bool) {
+    pub fn draw_rect(&mut self, rect: Rect<f32, f32>, color: impl Into<Color>, filled: bool) {
+        let color = color.into();
         match filled {
             true => {
-                for y in rect.y as i32..(rect.y + rect.h) as i32 {
-                    for x in rect.x as i32..(rect.x + rect.w) as i32 {
-                        // If this pixel is outside the framebuffer, skip it
-                        if x < 0 || y < 0 || x >= self.size.x as i32 || y >= self.size.y as i32 {
-                            continue;
-                        }
-                        self.framebuffer[y as usize * self.size.x + x as usize] = color;
+                for y in rect.y as i64..(rect.y + rect.h) as i64 {
+                    for x in rect.x as i64..(rect.x + rect.w) as i64 {
+                        self.draw_pixel(Vec2::new(x, y), color);
                     }
                 }
             }
@@ -74,84 +246,331 @@ impl<'tick> Graphics<'tick> {
     /// Draw a line. This takes a starting position and an ending position, and
     /// draws a line between them with the given color.
     /// TODO: Change this to internal implementation of Bresenham's algorithm
-    pub fn draw_line(&mut self, start: Vec2<i64>, end: Vec2<i64>, color: u32) {
+    pub fn draw_line(&mut self, start: Vec2<i64>, end: Vec2<i64>, color: impl Into<Color>) {
+        let color = color.into();
         for (x, y) in Bresenham::new((start.x, start.y), (end.x, end.y)) {
-            // If this pixel is outside the framebuffer, skip it
-            if x < 0 || y < 0 || x >= self.size.x as i64 || y >= self.size.y as i64 {
-                continue;
-            }
-            self.framebuffer[y as usize * self.size.x + x as usize] = color;
+            self.draw_pixel(Vec2::new(x, y), color);
         }
     }
 
     /// Draw a circle. This takes a center position and a radius, and draws a
     /// circle with the given color.
-    pub fn draw_circle(&mut self, center: Vec2<i64>, radius: i64, color: u32) {
+    pub fn draw_circle(&mut self, center: Vec2<i64>, radius: i64, color: impl Into<Color>) {
+        let color = color.into();
         for y in -radius..radius {
             for x in -radius..radius {
                 let pos = center + Vec2::new(x, y);
                 if (pos - center).magnitude_squared() <= radius * radius {
-                    // Make sure this pixel is inside the framebuffer
-                    if pos.x < 0
-                        || pos.y < 0
-                        || pos.x >= self.size.x as i64
-                        || pos.y >= self.size.y as i64
-                    {
+                    self.draw_pixel(pos, color);
+                }
+            }
+        }
+    }
+
+    /// Draw text using the built-in 3x5 pixel font, with the top-left corner of
+    /// the first character at the given position. Newlines start a new line
+    /// below the first.
+    pub fn draw_text(&mut self, text: &str, pos: Vec2<i64>, color: impl Into<Color>) {
+        let color = color.into();
+        let mut cursor = pos;
+        for c in text.chars() {
+            if c == '\n' {
+                cursor = Vec2::new(pos.x, cursor.y + font::ADVANCE_Y);
+                continue;
+            }
+
+            for (j, row) in font::glyph(c).iter().enumerate() {
+                for i in 0..font::GLYPH_W {
+                    if row & (1 << (font::GLYPH_W - 1 - i)) == 0 {
                         continue;
                     }
-
-                    self.framebuffer[pos.y as usize * self.size.x + pos.x as usize] = color;
+                    self.draw_pixel(cursor + Vec2::new(i, j as i64), color);
                 }
             }
+            cursor.x += font::ADVANCE_X;
         }
     }
 
+    /// Get the size, in pixels, that the given text will take up when drawn
+    /// with [`Graphics::draw_text`].
+    pub fn text_size(text: &str) -> Vec2<i64> {
+        let lines = text.split('\n');
+        let columns = lines
+            .clone()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0) as i64;
+        let rows = lines.count() as i64;
+        Vec2::new(
+            (columns * font::ADVANCE_X - 1).max(0),
+            rows * font::ADVANCE_Y - (font::ADVANCE_Y - font::GLYPH_H),
+        )
+    }
+
     /// Draw the given sprite at a position and with a frame index.
     ///
     /// If the frame index is greater than the number of frames, it will be looped animation will be looped.
     ///
     /// See [`sprite`] for information about loading sprites.
     pub fn draw_sprite(&mut self, sprite: &Sprite, pos: Vec2<i64>, frame: usize) {
+        sprite.reload_if_changed();
+        let img = sprite.img.read().unwrap();
+
         let frame = frame % sprite.frames;
-        let w = sprite.img.width() / sprite.frames as u32;
+        let w = img.width() / sprite.frames as u32;
+        // Sprites tend to have few colours, so remember which index each one
+        // is closest to
+        let mut indices = HashMap::new();
 
-        for j in 0..sprite.img.height() {
+        for j in 0..img.height() {
             for i in 0..w {
-                let p = sprite.img.get_pixel(i + w * frame as u32, j);
-                if p.0[3] == 0 {
+                let color = Color::from(*img.get_pixel(i + w * frame as u32, j));
+                if color.a == 0 {
                     continue;
                 }
 
-                let pos = Vec2::new(i as i64, j as i64) - sprite.center + pos;
-                // If this pixel is outside the framebuffer, skip it
-                if pos.x < 0
-                    || pos.y < 0
-                    || pos.x >= self.size.x as i64
-                    || pos.y >= self.size.y as i64
-                {
-                    continue;
+                let color = match self.palette.as_ref() {
+                    Some(palette) => *indices
+                        .entry(color)
+                        .or_insert_with(|| Color::index(palette.nearest(color))),
+                    None => color,
+                };
+                self.draw_pixel(Vec2::new(i as i64, j as i64) - sprite.center + pos, color);
+            }
+        }
+    }
+
+    /// Draw a whole canvas with its top-left corner at the given position.
+    pub fn draw_canvas(&mut self, canvas: &Canvas, pos: Vec2<i64>) {
+        let size = canvas.size().as_::<i64>();
+        self.blit(
+            canvas,
+            Rect::new(0, 0, size.x, size.y),
+            Rect::new(pos.x, pos.y, size.x, size.y),
+            BlendMode::Alpha,
+        );
+    }
+
+    /// Draw part of a canvas, `src`, stretched to fill `dst` and combined with
+    /// what's already been drawn by `mode`.
+    ///
+    /// If only one of the canvas and the framebuffer has a palette, the
+    /// canvas's palette indices are looked up as colours, or its colours are
+    /// swapped for the nearest indices. If both do, the indices are copied as
+    /// they are.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // Draw the minimap at half size in the corner
+    /// let size = self.minimap.size().as_::<i64>();
+    /// console.graphics.blit(
+    ///     &self.minimap,
+    ///     Rect::new(0, 0, size.x, size.y),
+    ///     Rect::new(150, 0, size.x / 2, size.y / 2),
+    ///     BlendMode::Alpha,
+    /// );
+    /// ```
+    pub fn blit(
+        &mut self,
+        canvas: &Canvas,
+        src: Rect<i64, i64>,
+        dst: Rect<i64, i64>,
+        mode: BlendMode,
+    ) {
+        if dst.w <= 0 || dst.h <= 0 {
+            return;
+        }
+        // Convert between palette indices and colours, if only one side uses
+        // them
+        let lookup = match (canvas.palette(), self.palette.is_some()) {
+            (Some(palette), false) => Some(palette.lookup()),
+            _ => None,
+        };
+        let target = match canvas.palette() {
+            Some(_) => None,
+            None => self.palette.clone(),
+        };
+        let mut indices = HashMap::new();
+
+        // Only visit the pixels of `dst` that are inside the clip rectangle
+        let View { clip, offset } = self.view();
+        let (min, max) = (
+            clip.position() - offset,
+            clip.position() + clip.extent() - offset,
+        );
+        for y in dst.y.max(min.y)..(dst.y + dst.h).min(max.y) {
+            for x in dst.x.max(min.x)..(dst.x + dst.w).min(max.x) {
+                // Take the pixel of `src` that this one is stretched from
+                let from = Vec2::new(
+                    src.x + (x - dst.x) * src.w / dst.w,
+                    src.y + (y - dst.y) * src.h / dst.h,
+                );
+                let color = match canvas.get(from) {
+                    Some(color) => color,
+                    None => continue,
+                };
+                let color = match (&lookup, &target) {
+                    (Some(lookup), _) => Color::from_pixel(lookup[color.b as usize]),
+                    (_, Some(palette)) => *indices
+                        .entry(color.with_alpha(255))
+                        .or_insert_with(|| Color::index(palette.nearest(color))),
+                    _ => color,
                 }
-                self.framebuffer[pos.y as usize * self.size.x + pos.x as usize] =
-                    u32::from_le_bytes([p.0[2], p.0[1], p.0[0], 0]);
+                .with_alpha(color.a);
+                self.blend_pixel(Vec2::new(x, y), color, mode);
             }
         }
     }
 }
 
+/// How a colour is combined with what has already been drawn, for
+/// [`Graphics::blit`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Draw over what's there, letting it show through translucent colours.
+    #[default]
+    Alpha,
+    /// Replace what's there, including its alpha when drawing to a
+    /// [`Canvas`].
+    Replace,
+    /// Brighten what's there by the colour, such as for lights and glows.
+    Add,
+    /// Darken what's there by the colour, such as for shadows and tints.
+    Multiply,
+}
+
+impl BlendMode {
+    /// The colour left by drawing `src` over `dst`.
+    fn blend(self, dst: Color, src: Color) -> Color {
+        match (self, src.a) {
+            (Self::Replace, _) | (Self::Alpha, 255) => return src,
+            (_, 0) => return dst,
+            _ => {}
+        }
+
+        let (sa, da) = (src.a as f32 / 255.0, dst.a as f32 / 255.0);
+        let alpha = sa + da * (1.0 - sa);
+        let channel = |s: u8, d: u8| {
+            let (s, d) = (s as f32 / 255.0, d as f32 / 255.0);
+            let c = match self {
+                Self::Add => d + s * sa,
+                Self::Multiply => d * (1.0 - sa + s * sa),
+                // Weigh each colour by how much of it shows
+                _ => (s * sa + d * da * (1.0 - sa)) / alpha,
+            };
+            (c.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+        Color::rgba(
+            channel(src.r, dst.r),
+            channel(src.g, dst.g),
+            channel(src.b, dst.b),
+            (alpha * 255.0).round() as u8,
+        )
+    }
+}
+
+/// How often a watched sprite checks whether its file has changed, in seconds.
+const WATCH_INTERVAL: f32 = 0.5;
+
 /// See [`sprite`].
 pub struct Sprite {
-    img: image::RgbaImage,
+    img: RwLock<image::RgbaImage>,
     center: Vec2<i64>,
     frames: usize,
+    watch: Option<Watch>,
+}
+
+/// The file a sprite is reloaded from when it changes.
+struct Watch {
+    path: PathBuf,
+    /// When the file was last modified, and when that was last checked.
+    state: Mutex<(Option<SystemTime>, Option<instant::Instant>)>,
 }
 
 impl Sprite {
     pub fn from_image(img: image::RgbaImage, center: Vec2<i64>, frames: usize) -> Self {
         Self {
-            img,
+            img: RwLock::new(img),
             center,
             frames,
+            watch: None,
+        }
+    }
+
+    /// Reload the sprite from a file whenever it changes, so that art can be
+    /// tweaked without recompiling the game. The sprite is loaded from the
+    /// file as soon as it's next drawn.
+    ///
+    /// This only has an effect in native debug builds with the `debug_tools`
+    /// feature. [`sprite`] calls this for the file it embeds.
+    pub fn watch(mut self, path: impl Into<PathBuf>) -> Self {
+        if cfg!(all(
+            feature = "debug_tools",
+            debug_assertions,
+            not(target_arch = "wasm32")
+        )) {
+            self.watch = Some(Watch {
+                path: path.into(),
+                state: Mutex::new((None, None)),
+            });
         }
+        self
+    }
+
+    fn reload_if_changed(&self) {
+        let watch = match &self.watch {
+            Some(watch) => watch,
+            None => return,
+        };
+
+        let mut state = watch.state.lock().unwrap();
+        let (modified, last_checked) = &mut *state;
+        if let Some(last_checked) = last_checked {
+            if last_checked.elapsed().as_secs_f32() < WATCH_INTERVAL {
+                return;
+            }
+        }
+        *last_checked = Some(instant::Instant::now());
+
+        let new_modified = std::fs::metadata(&watch.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if new_modified.is_none() || new_modified == *modified {
+            return;
+        }
+
+        // If this fails, the file may still be being written, so try again on
+        // the next check
+        match image::open(&watch.path) {
+            Ok(img) => {
+                *self.img.write().unwrap() = img.into_rgba8();
+                *modified = new_modified;
+            }
+            Err(err) => crate::dev_console::report_error(format!(
+                "Failed to reload {}: {}",
+                watch.path.display(),
+                err
+            )),
+        }
+    }
+
+    /// Find a file named relative to a source file, as [`include_bytes`] does.
+    ///
+    /// [`file!`] gives paths relative to the root of the workspace, which
+    /// isn't known, so this searches upwards from the crate's manifest for it.
+    #[doc(hidden)]
+    pub fn source_path(manifest_dir: &str, source_file: &str, path: &str) -> PathBuf {
+        let source_file = Path::new(source_file);
+        let source_file = Path::new(manifest_dir)
+            .ancestors()
+            .map(|dir| dir.join(source_file))
+            .find(|source_file| source_file.exists())
+            .unwrap_or_else(|| source_file.to_path_buf());
+        source_file
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(path)
     }
 }
 
@@ -163,6 +582,9 @@ impl Sprite {
 /// The `frames` parameter denotes the number of animation frames in the sprite. Frames can be added by extending the
 /// image file horizontally: for example, a 16x16 sprite with 10 frames of animation would be 160x16 pixels in size.
 ///
+/// The image is embedded in the game, but in debug builds it's also reloaded from disk whenever the file changes. See
+/// [`Sprite::watch`].
+///
 /// # Example
 ///
 /// ```ignore
@@ -194,6 +616,148 @@ macro_rules! sprite {
                 center,
                 frames,
             )
+            .watch(Sprite::source_path(::std::env!("CARGO_MANIFEST_DIR"), ::std::file!(), s))
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `f` with a graphics context drawing to a black framebuffer, then
+    /// return the framebuffer.
+    fn draw(size: Vec2<usize>, f: impl FnOnce(&mut Graphics)) -> Vec<u32> {
+        let mut framebuffer = vec![0x000000; size.product()];
+        f(&mut Graphics {
+            size,
+            framebuffer: &mut framebuffer,
+            palette: &mut None,
+            alpha: None,
+            views: Vec::new(),
+        });
+        framebuffer
+    }
+
+    #[test]
+    fn blend_modes() {
+        let (dst, half_red) = (Color::rgb(100, 100, 100), Color::RED.with_alpha(128));
+        assert_eq!(BlendMode::Alpha.blend(dst, Color::RED), Color::RED);
+        assert_eq!(BlendMode::Alpha.blend(dst, Color::TRANSPARENT), dst);
+        assert_eq!(
+            BlendMode::Alpha.blend(dst, half_red),
+            Color::rgb(178, 50, 50)
+        );
+        assert_eq!(BlendMode::Replace.blend(dst, half_red), half_red);
+        assert_eq!(
+            BlendMode::Add.blend(dst, Color::rgb(200, 50, 0)),
+            Color::rgb(255, 150, 100)
+        );
+        assert_eq!(
+            BlendMode::Multiply.blend(dst, Color::rgb(255, 0, 128)),
+            Color::rgb(100, 0, 50)
+        );
+    }
+
+    #[test]
+    fn blending_onto_transparency() {
+        // Only the colour being drawn shows, however translucent it is
+        let blended = BlendMode::Alpha.blend(Color::TRANSPARENT, Color::BLUE.with_alpha(64));
+        assert_eq!(blended, Color::BLUE.with_alpha(64));
+    }
+
+    #[test]
+    fn blit_scales() {
+        let mut canvas = Canvas::new(Vec2::new(2, 1));
+        canvas.graphics().draw_pixel(Vec2::new(1, 0), Color::RED);
+        let framebuffer = draw(Vec2::new(4, 2), |graphics| {
+            graphics.blit(
+                &canvas,
+                Rect::new(0, 0, 2, 1),
+                Rect::new(0, 0, 4, 2),
+                BlendMode::Alpha,
+            );
+        });
+        #[rustfmt::skip]
+        assert_eq!(framebuffer, [
+            0, 0, 0xFF0000, 0xFF0000,
+            0, 0, 0xFF0000, 0xFF0000,
+        ]);
+    }
+
+    #[test]
+    fn blit_between_palettes() {
+        let palette = Palette::new([Color::BLACK, Color::RED]);
+
+        // Indices are looked up when drawing to a full colour framebuffer
+        let mut indexed = Canvas::new(Vec2::new(1, 1));
+        let mut graphics = indexed.graphics();
+        graphics.set_palette(Some(palette.clone()));
+        graphics.clear(Color::index(1));
+        let framebuffer = draw(Vec2::new(1, 1), |graphics| {
+            graphics.draw_canvas(&indexed, Vec2::zero())
+        });
+        assert_eq!(framebuffer, [0xFF0000]);
+
+        // Colours are swapped for indices when drawing to an indexed one
+        let mut colors = Canvas::new(Vec2::new(1, 1));
+        colors.graphics().clear(Color::rgb(250, 10, 0));
+        let framebuffer = draw(Vec2::new(1, 1), |graphics| {
+            graphics.set_palette(Some(palette));
+            graphics.draw_canvas(&colors, Vec2::zero())
+        });
+        assert_eq!(framebuffer, [Color::index(1).to_pixel()]);
+    }
+
+    #[test]
+    fn clip_and_offset() {
+        let framebuffer = draw(Vec2::new(4, 4), |graphics| {
+            graphics.push_offset(Vec2::new(1, 1));
+            graphics.push_clip(Rect::new(0, 0, 2, 5));
+            graphics.draw_rect(Rect::new(-5.0, -5.0, 10.0, 10.0), 0xFFFFFF, true);
+            graphics.pop();
+            graphics.pop();
+            graphics.draw_pixel(Vec2::new(0, 0), 0xFF0000);
+        });
+        #[rustfmt::skip]
+        assert_eq!(framebuffer, [
+            0xFF0000, 0, 0, 0,
+            0, 0xFFFFFF, 0xFFFFFF, 0,
+            0, 0xFFFFFF, 0xFFFFFF, 0,
+            0, 0xFFFFFF, 0xFFFFFF, 0,
+        ]);
+    }
+
+    #[test]
+    fn nested_clips_intersect() {
+        draw(Vec2::new(10, 10), |graphics| {
+            graphics.push_clip(Rect::new(2, 2, 6, 6));
+            graphics.push_clip(Rect::new(0, 4, 5, 10));
+            assert_eq!(graphics.view().clip, Rect::new(2, 4, 3, 4));
+        });
+    }
+
+    #[test]
+    fn clips_outside_the_framebuffer() {
+        let framebuffer = draw(Vec2::new(320, 240), |graphics| {
+            graphics.push_clip(Rect::new(400, 230, 10, 10));
+            assert_eq!(graphics.view().clip, Rect::new(0, 0, 0, 0));
+            graphics.clear(0xFFFFFF);
+            graphics.draw_pixel(Vec2::new(0, 0), 0xFFFFFF);
+            graphics.pop();
+
+            graphics.push_clip(Rect::new(-20, -20, 10, 10));
+            graphics.clear(0xFFFFFF);
+        });
+        assert!(framebuffer.iter().all(|pixel| *pixel == 0x000000));
+    }
+
+    #[test]
+    fn clear_fills_the_clip() {
+        let framebuffer = draw(Vec2::new(3, 2), |graphics| {
+            graphics.push_clip(Rect::new(1, 1, 5, 5));
+            graphics.clear(0xFFFFFF);
+        });
+        assert_eq!(framebuffer, [0, 0, 0, 0, 0xFFFFFF, 0xFFFFFF]);
+    }
+}
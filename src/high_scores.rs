@@ -0,0 +1,217 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vek::Vec2;
+
+use crate::{font, graphics::Graphics, storage, Color, SaveError};
+
+const HIGH_SCORES_PATH: &str = "high_scores.json";
+
+/// A single entry in a high-score table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: i64,
+    /// When the score was achieved, in seconds since the Unix epoch.
+    pub date: u64,
+    /// Anything else the game wants to remember about the score, such as the
+    /// level it was achieved on.
+    pub metadata: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Board {
+    capacity: usize,
+    entries: Vec<HighScore>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            capacity: HighScores::DEFAULT_CAPACITY,
+            entries: Vec::new(),
+        }
+    }
+}
+
+/// Local high-score tables, kept separately from [`crate::Save`].
+///
+/// A game can keep several named boards, such as one per level. Each board is
+/// sorted from highest to lowest score, and only keeps its best
+/// [`HighScores::DEFAULT_CAPACITY`] entries unless told otherwise with
+/// [`HighScores::set_capacity`]. Call [`HighScores::save`] to persist the
+/// tables after submitting a score.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    boards: BTreeMap<String, Board>,
+}
+
+impl HighScores {
+    /// The number of entries a board keeps, unless changed with
+    /// [`HighScores::set_capacity`].
+    pub const DEFAULT_CAPACITY: usize = 10;
+
+    /// Load the high scores, starting with empty tables if they haven't been
+    /// saved yet or couldn't be read.
+    pub(crate) fn load() -> Self {
+        storage::load(HIGH_SCORES_PATH)
+            .and_then(|bytes| serde_json::from_slice(&bytes).map_err(SaveError::corrupt))
+            .unwrap_or_default()
+    }
+
+    /// Persist the high scores, so that they are still there the next time the
+    /// game starts.
+    pub fn save(&self) -> Result<(), SaveError> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(SaveError::corrupt)?;
+        storage::store(HIGH_SCORES_PATH, &bytes)
+    }
+
+    /// Change the number of entries a board keeps, dropping the lowest scores
+    /// if it's now over capacity.
+    pub fn set_capacity(&mut self, board: &str, capacity: usize) {
+        let board = self.boards.entry(board.to_string()).or_default();
+        board.capacity = capacity;
+        board.entries.truncate(capacity);
+    }
+
+    /// The entries on a board, from highest to lowest score.
+    pub fn entries(&self, board: &str) -> &[HighScore] {
+        self.boards
+            .get(board)
+            .map(|board| &board.entries[..])
+            .unwrap_or(&[])
+    }
+
+    /// The position, starting at 0, that a score would take on a board, or
+    /// `None` if it isn't good enough to make it on. Ties are placed below the
+    /// existing entries.
+    pub fn rank(&self, board: &str, score: i64) -> Option<usize> {
+        let (capacity, entries) = match self.boards.get(board) {
+            Some(board) => (board.capacity, &board.entries[..]),
+            None => (Self::DEFAULT_CAPACITY, &[][..]),
+        };
+        let rank = entries.partition_point(|entry| entry.score >= score);
+        (rank < capacity).then_some(rank)
+    }
+
+    /// Whether a score is good enough to make it on to a board.
+    pub fn is_high_score(&self, board: &str, score: i64) -> bool {
+        self.rank(board, score).is_some()
+    }
+
+    /// Whether a score would beat every entry on a board.
+    pub fn is_best(&self, board: &str, score: i64) -> bool {
+        match self.entries(board).first() {
+            Some(best) => score > best.score,
+            None => true,
+        }
+    }
+
+    /// Add a score to a board, returning its position, or `None` if it wasn't
+    /// good enough to make it on.
+    pub fn submit(
+        &mut self,
+        board: &str,
+        name: impl Into<String>,
+        score: i64,
+        metadata: Option<String>,
+    ) -> Option<usize> {
+        let rank = self.rank(board, score)?;
+        let board = self.boards.entry(board.to_string()).or_default();
+        board.entries.insert(
+            rank,
+            HighScore {
+                name: name.into(),
+                score,
+                date: crate::unix_time_ms() / 1000,
+                metadata,
+            },
+        );
+        board.entries.truncate(board.capacity);
+        Some(rank)
+    }
+
+    /// Remove every entry from a board.
+    pub fn clear(&mut self, board: &str) {
+        if let Some(board) = self.boards.get_mut(board) {
+            board.entries.clear();
+        }
+    }
+
+    /// Draw a board as a numbered list of names and scores, with its top-left
+    /// corner at the given position.
+    pub fn draw(
+        &self,
+        board: &str,
+        graphics: &mut Graphics,
+        pos: Vec2<i64>,
+        color: impl Into<Color>,
+    ) {
+        let color = color.into();
+        let entries = self.entries(board);
+        let name_width = entries
+            .iter()
+            .map(|entry| entry.name.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for (i, entry) in entries.iter().enumerate() {
+            let line = format!(
+                "{:>2}. {:<width$} {}",
+                i + 1,
+                entry.name,
+                entry.score,
+                width = name_width
+            );
+            graphics.draw_text(&line, pos + Vec2::new(0, i as i64 * font::ADVANCE_Y), color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(high_scores: &HighScores, board: &str) -> Vec<i64> {
+        high_scores
+            .entries(board)
+            .iter()
+            .map(|entry| entry.score)
+            .collect()
+    }
+
+    #[test]
+    fn sorted_highest_first() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.submit("a", "B", 20, None), Some(0));
+        assert_eq!(high_scores.submit("a", "C", 30, None), Some(0));
+        assert_eq!(high_scores.submit("a", "D", 10, None), Some(2));
+        assert_eq!(scores(&high_scores, "a"), [30, 20, 10]);
+        assert!(high_scores.entries("b").is_empty());
+    }
+
+    #[test]
+    fn ties_go_below() {
+        let mut high_scores = HighScores::default();
+        high_scores.submit("a", "First", 10, None);
+        assert_eq!(high_scores.submit("a", "Second", 10, None), Some(1));
+        assert_eq!(high_scores.entries("a")[0].name, "First");
+        assert!(!high_scores.is_best("a", 10));
+        assert!(high_scores.is_best("a", 11));
+    }
+
+    #[test]
+    fn capacity() {
+        let mut high_scores = HighScores::default();
+        high_scores.set_capacity("a", 2);
+        for score in [5, 3, 4] {
+            high_scores.submit("a", "P", score, None);
+        }
+        assert_eq!(scores(&high_scores, "a"), [5, 4]);
+        assert_eq!(high_scores.rank("a", 3), None);
+        assert!(!high_scores.is_high_score("a", 4));
+        assert_eq!(high_scores.submit("a", "P", 1, None), None);
+
+        high_scores.set_capacity("a", 1);
+        assert_eq!(scores(&high_scores, "a"), [5]);
+    }
+}
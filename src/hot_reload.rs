@@ -0,0 +1,261 @@
+//! Reloading a game's logic from a dynamic library while it's running.
+
+#[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+pub(crate) use loader::HotReload;
+
+/// Export a game from a dynamic library, so that the engine can reload its
+/// logic while it's running.
+///
+/// The game's crate is built as both a library, which calls this macro, and a
+/// binary, which runs the game. When the binary is run with the `hot_reload`
+/// feature and `--hot-reload <library>`, the game's `init` and `tick` are
+/// called from the library rather than the binary. Whenever the library is
+/// rebuilt, the game's state is serialized with the old code and deserialized
+/// with the new, so the game carries on where it left off.
+///
+/// Only the game's code can change this way. The binary still has its own copy
+/// of the game's type, so adding, removing or changing the game's fields needs
+/// a restart. A library whose game type has a different size or alignment is
+/// refused, but changes that keep those the same can't be caught.
+///
+/// Commands, snapshots and scenes are set up by code in the library, so they
+/// are cleared when it's reloaded.
+///
+/// # Example
+///
+/// ```ignore
+/// // src/lib.rs, with `crate-type = ["rlib", "cdylib"]`
+/// #[derive(Serialize, Deserialize)]
+/// pub struct Pong { ... }
+///
+/// micro_jam_engine::hot_reload!(Pong);
+/// ```
+///
+/// ```sh
+/// cargo build --lib --features hot_reload
+/// cargo run --features hot_reload -- --hot-reload target/debug/libpong.so
+/// ```
+#[macro_export]
+macro_rules! hot_reload {
+    ($game:ty) => {
+        #[no_mangle]
+        pub fn micro_jam_engine_init(console: &mut $crate::Console<'_, $game>) -> *mut $game {
+            ::std::boxed::Box::into_raw(::std::boxed::Box::new(<$game as $crate::Game>::init(
+                console,
+            )))
+        }
+
+        #[no_mangle]
+        pub fn micro_jam_engine_tick(
+            game: &mut $game,
+            dt: f32,
+            console: &mut $crate::Console<'_, $game>,
+        ) {
+            <$game as $crate::Game>::tick(game, dt, console)
+        }
+
+        #[no_mangle]
+        pub fn micro_jam_engine_lifecycle(
+            game: &mut $game,
+            event: $crate::Lifecycle,
+            console: &mut $crate::Console<'_, $game>,
+        ) -> bool {
+            event.dispatch(game, console)
+        }
+
+        #[no_mangle]
+        pub fn micro_jam_engine_layout() -> (usize, usize) {
+            (
+                ::std::mem::size_of::<$game>(),
+                ::std::mem::align_of::<$game>(),
+            )
+        }
+
+        #[no_mangle]
+        pub fn micro_jam_engine_save(game: &$game) -> Result<Vec<u8>, $crate::SaveError> {
+            $crate::SaveFormat::Bincode.encode(game)
+        }
+
+        #[no_mangle]
+        pub fn micro_jam_engine_load(bytes: &[u8]) -> Result<*mut $game, $crate::SaveError> {
+            $crate::SaveFormat::Bincode
+                .decode::<$game>(bytes)
+                .map(|game| ::std::boxed::Box::into_raw(::std::boxed::Box::new(game)))
+        }
+
+        /// # Safety
+        ///
+        /// `game` must have been created by this library, and not freed yet.
+        #[no_mangle]
+        pub unsafe fn micro_jam_engine_free(game: *mut $game) {
+            ::std::mem::drop(::std::boxed::Box::from_raw(game))
+        }
+    };
+}
+
+#[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+mod loader {
+    use libloading::Library;
+    use std::{
+        path::{Path, PathBuf},
+        time::SystemTime,
+    };
+
+    use crate::{Console, Game, Lifecycle, SaveError};
+
+    /// How often to check whether the library has been rebuilt, in seconds.
+    const CHECK_INTERVAL: f32 = 0.5;
+
+    /// The functions exported by [`crate::hot_reload!`].
+    pub(crate) struct Api<G: Game> {
+        pub(crate) init: fn(&mut Console<'_, G>) -> *mut G,
+        pub(crate) tick: fn(&mut G, f32, &mut Console<'_, G>),
+        pub(crate) lifecycle: fn(&mut G, Lifecycle, &mut Console<'_, G>) -> bool,
+        layout: fn() -> (usize, usize),
+        save: fn(&G) -> Result<Vec<u8>, SaveError>,
+        load: fn(&[u8]) -> Result<*mut G, SaveError>,
+        free: unsafe fn(*mut G),
+    }
+
+    /// A loaded copy of the library. The library is copied before it's
+    /// loaded, so that it can be rebuilt while the game is running.
+    pub(crate) struct Loaded<G: Game> {
+        pub(crate) api: Api<G>,
+        library: Option<Library>,
+        copy: PathBuf,
+    }
+
+    impl<G: Game> Loaded<G> {
+        fn open(path: &Path, copy: PathBuf) -> Result<Self, String> {
+            std::fs::copy(path, &copy).map_err(|err| err.to_string())?;
+
+            // Safety: the library is trusted to export the functions that
+            // `hot_reload!` generates, for the same game type
+            unsafe {
+                let library = Library::new(&copy).map_err(|err| err.to_string())?;
+                Ok(Self {
+                    api: Api {
+                        init: symbol(&library, b"micro_jam_engine_init\0")?,
+                        tick: symbol(&library, b"micro_jam_engine_tick\0")?,
+                        lifecycle: symbol(&library, b"micro_jam_engine_lifecycle\0")?,
+                        layout: symbol(&library, b"micro_jam_engine_layout\0")?,
+                        save: symbol(&library, b"micro_jam_engine_save\0")?,
+                        load: symbol(&library, b"micro_jam_engine_load\0")?,
+                        free: symbol(&library, b"micro_jam_engine_free\0")?,
+                    },
+                    library: Some(library),
+                    copy,
+                })
+            }
+        }
+
+        /// Check that the library's game type is laid out like the one the
+        /// engine was built with, as far as can be told.
+        fn check_layout(&self) -> Result<(), String> {
+            let layout = (std::mem::size_of::<G>(), std::mem::align_of::<G>());
+            match (self.api.layout)() == layout {
+                true => Ok(()),
+                false => Err("the game's type has changed, so it needs restarting".to_string()),
+            }
+        }
+    }
+
+    impl<G: Game> Drop for Loaded<G> {
+        fn drop(&mut self) {
+            // The library has to be unloaded before its copy can be removed
+            drop(self.library.take());
+            let _ = std::fs::remove_file(&self.copy);
+        }
+    }
+
+    unsafe fn symbol<T: Copy>(library: &Library, name: &[u8]) -> Result<T, String> {
+        library
+            .get::<T>(name)
+            .map(|symbol| *symbol)
+            .map_err(|err| err.to_string())
+    }
+
+    /// A game library that is reloaded whenever it's rebuilt.
+    pub(crate) struct HotReload<G: Game> {
+        path: PathBuf,
+        modified: Option<SystemTime>,
+        last_checked: instant::Instant,
+        /// The number of times the library has been loaded, to give each copy
+        /// a unique name.
+        loads: usize,
+        pub(crate) loaded: Loaded<G>,
+    }
+
+    impl<G: Game> HotReload<G> {
+        pub(crate) fn load(path: &str) -> Result<Self, String> {
+            let path = PathBuf::from(path);
+            let modified = modified(&path);
+            let loaded = Loaded::open(&path, copy_path(&path, 0))?;
+            loaded.check_layout()?;
+            Ok(Self {
+                path,
+                modified,
+                last_checked: instant::Instant::now(),
+                loads: 1,
+                loaded,
+            })
+        }
+
+        /// Reload the library if it has been rebuilt, moving the game's state
+        /// over to the new code. Returns the old library, which must not be
+        /// dropped until anything that the game has left with the engine has
+        /// been cleared.
+        pub(crate) fn reload_if_changed(
+            &mut self,
+            game: &mut Box<G>,
+        ) -> Option<Result<Loaded<G>, String>> {
+            if self.last_checked.elapsed().as_secs_f32() < CHECK_INTERVAL {
+                return None;
+            }
+            self.last_checked = instant::Instant::now();
+
+            let modified = modified(&self.path);
+            if modified == self.modified {
+                return None;
+            }
+
+            // If this fails, the library may still be being written, so try
+            // again on the next check
+            let loaded = match Loaded::open(&self.path, copy_path(&self.path, self.loads)) {
+                Ok(loaded) => loaded,
+                Err(err) => return Some(Err(err)),
+            };
+            self.loads += 1;
+            self.modified = modified;
+            if let Err(err) = loaded.check_layout() {
+                return Some(Err(err));
+            }
+
+            let state = (self.loaded.api.save)(game).and_then(|bytes| (loaded.api.load)(&bytes));
+            match state {
+                Ok(state) => {
+                    // Safety: the old state was created by the old library, and
+                    // the new state by the new library, which now owns it
+                    let old = std::mem::replace(game, unsafe { Box::from_raw(state) });
+                    unsafe { (self.loaded.api.free)(Box::into_raw(old)) };
+                    Some(Ok(std::mem::replace(&mut self.loaded, loaded)))
+                }
+                Err(err) => Some(Err(format!(
+                    "couldn't carry the game's state over: {}",
+                    err
+                ))),
+            }
+        }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn copy_path(path: &Path, loads: usize) -> PathBuf {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        std::env::temp_dir().join(format!("{}-{}-{}", std::process::id(), loads, name))
+    }
+}
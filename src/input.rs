@@ -1,5 +1,12 @@
+use serde_derive::{Deserialize, Serialize};
 use std::ops::Deref;
-use winit::{dpi::PhysicalPosition, event::KeyboardInput};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{
+        ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase,
+        WindowEvent,
+    },
+};
 use winit_input_helper::WinitInputHelper;
 
 #[derive(Clone)]
@@ -17,15 +24,51 @@ impl Deref for Input {
 }
 
 impl Input {
+    /// Input with nothing pressed, for when the game is called outside of a
+    /// tick.
+    pub(crate) fn idle() -> Self {
+        Self {
+            input_queue: Vec::new(),
+            input_helper: WinitInputHelper::new(),
+        }
+    }
+
+    /// The characters typed since the last tick. Backspace is typed as
+    /// `'\u{8}'`.
+    pub fn text(&self) -> impl Iterator<Item = char> + '_ {
+        self.input_queue.iter().filter_map(|event| match event {
+            InputEvent::ReceivedCharacter(c) => Some(*c),
+            _ => None,
+        })
+    }
+
     //pub fn key(&self, key: Key) -> KeyState { todo!() }
     //pub fn key_presses(&self) -> impl Iterator<Item = Key>;
     //pub fn axis(&self, axis: Axis) -> AxisState { todo!() }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum InputEvent {
     KeyboardInput(KeyboardInput),
     CursorMoved(PhysicalPosition<f64>),
+    MouseInput {
+        state: ElementState,
+        button: MouseButton,
+    },
+    ReceivedCharacter(char),
+    MouseWheel {
+        delta: MouseScrollDelta,
+        phase: TouchPhase,
+    },
+    ModifiersChanged(ModifiersState),
+    Resized(PhysicalSize<u32>),
+    ScaleFactorChanged {
+        scale_factor: f64,
+        new_inner_size: PhysicalSize<u32>,
+    },
+    Focused(bool),
+    CloseRequested,
+    Destroyed,
 }
 
 pub enum KeyState {
@@ -34,6 +77,32 @@ pub enum KeyState {
 }
 
 impl InputEvent {
+    /// The input in a window event, if it's one that [`WinitInputHelper`]
+    /// reads. Replays record every one of these, so that the input helper
+    /// ends up in the same state when they're played back.
+    pub(crate) fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match *event {
+            WindowEvent::KeyboardInput { input, .. } => Self::KeyboardInput(input),
+            WindowEvent::CursorMoved { position, .. } => Self::CursorMoved(position),
+            WindowEvent::MouseInput { state, button, .. } => Self::MouseInput { state, button },
+            WindowEvent::ReceivedCharacter(c) => Self::ReceivedCharacter(c),
+            WindowEvent::MouseWheel { delta, phase, .. } => Self::MouseWheel { delta, phase },
+            WindowEvent::ModifiersChanged(modifiers) => Self::ModifiersChanged(modifiers),
+            WindowEvent::Resized(size) => Self::Resized(size),
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                ref new_inner_size,
+            } => Self::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size: **new_inner_size,
+            },
+            WindowEvent::Focused(focused) => Self::Focused(focused),
+            WindowEvent::CloseRequested => Self::CloseRequested,
+            WindowEvent::Destroyed => Self::Destroyed,
+            _ => return None,
+        })
+    }
+
     // Check if a key is pressed
     // pub fn key(&self, key: VirtualKeyCode) -> KeyState { todo!() }
     // pub fn key_presses(&self) -> impl Iterator<Item = Key>;
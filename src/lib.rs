@@ -1,28 +1,88 @@
+use capture::{Capture, Recording};
+use dev_console::DevConsole;
 use graphics::Graphics;
 use input::InputEvent;
+use options::Options;
 use prelude::Input;
+use replay::{Interruption, Player, Replay};
+use scenes::SceneStack;
+use time_control::TimeControl;
 
 use serde::{de::DeserializeOwned, Serialize};
-use std::marker::PhantomData;
 use wasm_bindgen::prelude::*;
-use winit::event::{Event, WindowEvent};
+use winit::event::{Event, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::WindowBuilder;
+use winit::window::{Fullscreen, WindowBuilder};
 use winit_input_helper::WinitInputHelper;
 
 pub use vek;
 
 use vek::*;
 
+mod canvas;
+mod capture;
+mod color;
+mod debug;
+mod dev_console;
+mod filters;
+mod font;
 mod graphics;
+mod high_scores;
+mod hot_reload;
 pub mod input;
+mod options;
+mod palette;
+mod replay;
+mod rng;
+mod save;
+mod scenes;
+mod settings;
+mod snapshots;
+mod storage;
+mod time_control;
+mod timing;
+mod transition;
+pub mod upscale;
+
+pub use canvas::Canvas;
+pub use capture::RecordingFormat;
+pub use color::Color;
+pub use debug::Debug;
+pub use dev_console::{Args, Commands};
+pub use filters::{Filters, Mask};
+pub use high_scores::{HighScore, HighScores};
+pub use palette::{Palette, PaletteError};
+pub use rng::{Rng, Uniform};
+pub use save::{Save, SaveError, SaveFormat};
+pub use scenes::{Scene, Scenes};
+pub use settings::Settings;
+pub use snapshots::Snapshots;
+pub use timing::{FramePacing, Timing};
+pub use transition::Transition;
+pub use upscale::Upscaler;
 
 pub use image as _image;
 
 pub mod prelude {
+    pub use crate::canvas::Canvas;
+    pub use crate::capture::RecordingFormat;
+    pub use crate::color::Color;
+    pub use crate::debug::Debug;
+    pub use crate::dev_console::{Args, Commands};
+    pub use crate::filters::{Filters, Mask};
     pub use crate::graphics::*;
+    pub use crate::high_scores::*;
     pub use crate::input::*;
-    pub use crate::{sprite, Console, Game};
+    pub use crate::palette::{Palette, PaletteError};
+    pub use crate::rng::*;
+    pub use crate::save::*;
+    pub use crate::scenes::{Scene, Scenes};
+    pub use crate::settings::*;
+    pub use crate::snapshots::Snapshots;
+    pub use crate::timing::{FramePacing, Timing};
+    pub use crate::transition::Transition;
+    pub use crate::upscale::Upscaler;
+    pub use crate::{palette, sprite, Console, Game};
     pub use lazy_static::lazy_static;
     pub use vek::*;
     pub use winit;
@@ -48,20 +108,119 @@ pub trait Game: Sized + 'static {
     const TITLE: &'static str;
     type SaveData: Default + Serialize + DeserializeOwned;
 
+    /// The version of [`Game::SaveData`] written by this build of the game.
+    /// Bump this whenever the save data changes in a way that old saves can no
+    /// longer be read, and upgrade them in [`Game::migrate_save`].
+    const SAVE_VERSION: u32 = 0;
+
+    /// The encoding used to store [`Game::SaveData`]. Use one of the text
+    /// formats to make saves editable by hand.
+    const SAVE_FORMAT: SaveFormat = SaveFormat::Bincode;
+
+    /// The seed for [`Console::rng`]. If `None`, a seed is picked at startup.
+    /// Either can be overridden with the `--seed <number>` command line
+    /// argument.
+    const SEED: Option<u64> = None;
+
+    /// Whether the engine stops ticking the game while its window doesn't
+    /// have focus.
+    const PAUSE_ON_FOCUS_LOST: bool = false;
+
+    /// How often the game is ticked. Can be changed while the game is running
+    /// with [`Console::timing`].
+    const FRAME_PACING: FramePacing = FramePacing::Fixed(60.0);
+
+    /// Post-processing applied as the game is upscaled to the window, such as
+    /// CRT scanlines. Can be changed while the game is running with
+    /// [`Console::filters`].
+    const FILTERS: Filters = Filters::NONE;
+
+    /// How the game is enlarged to fill the window. Can be changed while the
+    /// game is running with [`Console::upscaler`].
+    const UPSCALER: Upscaler = Upscaler::Nearest;
+
+    /// Upgrade the raw bytes of a save written with an older
+    /// [`Game::SAVE_VERSION`] into the current save data.
+    ///
+    /// The bytes are encoded in [`Game::SAVE_FORMAT`]. By default, they are
+    /// decoded as if they were the current version.
+    fn migrate_save(version: u32, bytes: &[u8]) -> Result<Self::SaveData, SaveError> {
+        let _ = version;
+        Self::SAVE_FORMAT.decode(bytes)
+    }
+
     fn init(console: &mut Console<Self>) -> Self;
 
     fn tick(&mut self, dt: f32, console: &mut Console<Self>);
 
+    /// Called when the player closes the window, or the game calls
+    /// [`Console::quit`]. This is a chance to autosave. Return `false` to keep
+    /// running instead, such as to ask the player whether they're sure.
+    fn on_quit(&mut self, _console: &mut Console<Self>) -> bool {
+        true
+    }
+
+    /// Called when the game's window loses focus. See also
+    /// [`Game::PAUSE_ON_FOCUS_LOST`].
+    fn on_focus_lost(&mut self, _console: &mut Console<Self>) {}
+
+    fn on_focus_gained(&mut self, _console: &mut Console<Self>) {}
+
+    /// Called when the game is put in the background, such as when its
+    /// browser tab is hidden. The game isn't ticked until it's resumed.
+    fn on_suspend(&mut self, _console: &mut Console<Self>) {}
+
+    fn on_resume(&mut self, _console: &mut Console<Self>) {}
+
     fn run() {
         run_with::<Self>()
     }
 }
 
+/// Something that happens to the game outside of its tick, passed to the
+/// matching [`Game`] hook.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub enum Lifecycle {
+    Quit,
+    FocusLost,
+    FocusGained,
+    Suspend,
+    Resume,
+}
+
+impl Lifecycle {
+    /// Call the game's hook for this event. Returns `false` if the game
+    /// refused to quit.
+    pub fn dispatch<G: Game>(self, game: &mut G, console: &mut Console<G>) -> bool {
+        match self {
+            Self::Quit => return game.on_quit(console),
+            Self::FocusLost => game.on_focus_lost(console),
+            Self::FocusGained => game.on_focus_gained(console),
+            Self::Suspend => game.on_suspend(console),
+            Self::Resume => game.on_resume(console),
+        }
+        true
+    }
+}
+
 pub struct Console<'tick, G: Game> {
     pub input: Input,
     pub graphics: Graphics<'tick>,
     pub audio: Audio,
     pub save: Save<G::SaveData>,
+    pub settings: &'tick mut Settings,
+    pub high_scores: &'tick mut HighScores,
+    pub rng: &'tick mut Rng,
+    pub debug: &'tick mut Debug,
+    pub timing: &'tick mut Timing,
+    pub filters: &'tick mut Filters,
+    pub upscaler: &'tick mut Upscaler,
+    pub commands: &'tick mut Commands<G>,
+    pub snapshots: &'tick mut Snapshots<G>,
+    pub scenes: &'tick mut Scenes<G>,
+    capture: &'tick mut Capture,
+    quit: &'tick mut bool,
     tick: usize,
 }
 
@@ -69,47 +228,462 @@ impl<'tick, G: Game> Console<'tick, G> {
     pub fn tick(&self) -> usize {
         self.tick
     }
+
+    /// Save a screenshot of the framebuffer once this tick has finished
+    /// drawing. Natively, it's written to a timestamped PNG in the working
+    /// directory; on the web, it's downloaded by the browser.
+    ///
+    /// Players can also take screenshots with the `"screenshot"` key binding,
+    /// which defaults to F12. Hold shift to take an upscaled screenshot.
+    pub fn screenshot(&mut self) {
+        self.capture.screenshot = Some(false);
+    }
+
+    /// Like [`Console::screenshot`], but upscaled to the size the game is
+    /// shown at in the window.
+    pub fn screenshot_upscaled(&mut self) {
+        self.capture.screenshot = Some(true);
+    }
+
+    /// Start recording gameplay, keeping the frames drawn over the last
+    /// `seconds`. Any recording already in progress is discarded.
+    ///
+    /// Players can also start and stop a 10 second GIF recording with the
+    /// `"record"` key binding, which defaults to F10.
+    pub fn start_recording(&mut self, format: RecordingFormat, seconds: f32) {
+        self.capture.recording = Some(Recording::new(format, seconds, self.graphics.size));
+    }
+
+    /// Stop recording, and export the frames that were kept in the same way as
+    /// [`Console::screenshot`].
+    pub fn stop_recording(&mut self) {
+        if let Some(recording) = self.capture.recording.take() {
+            recording.finish();
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.capture.recording.is_some()
+    }
+
+    /// Quit the game once this tick has finished, after checking with
+    /// [`Game::on_quit`].
+    pub fn quit(&mut self) {
+        *self.quit = true;
+    }
 }
 
-pub struct Audio;
+pub struct Audio {
+    volume: f32,
+}
 
 impl Audio {
     //pub fn play(&mut self, sound: Sound) { todo!() }
+
+    /// The master volume, taken from [`Settings::volume`].
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
 }
 
-pub struct Save<S> {
-    phantom: PhantomData<S>,
+const W: usize = 200;
+const H: usize = 150;
+
+/// The largest whole scale that the framebuffer can be shown at in a window of
+/// the given size.
+fn fit_scale(width: usize, height: usize) -> usize {
+    (width / W).min(height / H).max(1)
 }
 
-impl<S: Default + Serialize + DeserializeOwned> Save<S> {
-    pub fn read(&mut self) -> S {
-        #[cfg(target_arch = "wasm32")]
-        {
-            S::default()
+/// The current time, in milliseconds since the Unix epoch.
+pub(crate) fn unix_time_ms() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now() as u64
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// The state the engine keeps between ticks, lent to the game through a
+/// [`Console`].
+struct Engine<G: Game> {
+    framebuffer: Vec<u32>,
+    /// The palette the framebuffer indexes into, if the game is drawing in
+    /// indexed colour.
+    palette: Option<Palette>,
+    settings: Settings,
+    high_scores: HighScores,
+    rng: Rng,
+    debug: Debug,
+    timing: Timing,
+    filters: Filters,
+    upscaler: Upscaler,
+    time_control: TimeControl,
+    commands: Commands<G>,
+    snapshots: Snapshots<G>,
+    scenes: Scenes<G>,
+    scene_stack: SceneStack<G>,
+    dev_console: DevConsole,
+    capture: Capture,
+    /// The replay being recorded, and the file it will be written to.
+    recording_replay: Option<(String, Replay)>,
+    /// Whether the game has asked to quit.
+    quit: bool,
+    /// Whether the game is in the background, and so not being ticked.
+    suspended: bool,
+    /// Whether ticking has stopped because the window lost focus.
+    focus_paused: bool,
+    /// The library the game's logic is loaded from, if it's being hot
+    /// reloaded.
+    #[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+    hot_reload: Option<hot_reload::HotReload<G>>,
+    tick: usize,
+}
+
+impl<G: Game> Engine<G> {
+    fn new(options: &Options, seed: u64) -> Self {
+        Self {
+            framebuffer: vec![0; W * H],
+            palette: None,
+            settings: Settings::load(),
+            high_scores: HighScores::load(),
+            rng: Rng::new(seed),
+            debug: Debug::default(),
+            timing: Timing::new(match options.uncapped {
+                true => FramePacing::Uncapped,
+                false => G::FRAME_PACING,
+            }),
+            filters: G::FILTERS,
+            upscaler: G::UPSCALER,
+            time_control: TimeControl::default(),
+            commands: Commands::default(),
+            snapshots: Snapshots::default(),
+            scenes: Scenes::default(),
+            scene_stack: SceneStack::default(),
+            dev_console: DevConsole::default(),
+            capture: Capture::default(),
+            recording_replay: options
+                .record_replay
+                .clone()
+                .map(|path| (path, Replay::new(seed))),
+            quit: false,
+            suspended: false,
+            focus_paused: false,
+            #[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+            hot_reload: options.hot_reload.as_deref().and_then(|path| {
+                match hot_reload::HotReload::load(path) {
+                    Ok(hot_reload) => Some(hot_reload),
+                    Err(err) => {
+                        dev_console::report_error(format!("Failed to load {}: {}", path, err));
+                        None
+                    }
+                }
+            }),
+            tick: 0,
+        }
+    }
+
+    /// Create the game, with the hot reloaded library's code if there is one.
+    fn init(&mut self) -> Box<G> {
+        #[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+        let hot_init = self.hot_reload.as_ref().map(|h| h.loaded.api.init);
+
+        let mut console = self.console(Input::idle());
+
+        #[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+        if let Some(init) = hot_init {
+            // Safety: the library's state is only freed by the library, when
+            // it's reloaded
+            return unsafe { Box::from_raw(init(&mut console)) };
+        }
+
+        Box::new(G::init(&mut console))
+    }
+
+    /// Reload the game's logic if its library has been rebuilt.
+    #[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+    fn reload(&mut self, game: &mut Box<G>) {
+        let result = match &mut self.hot_reload {
+            Some(hot_reload) => hot_reload.reload_if_changed(game),
+            None => None,
+        };
+        match result {
+            Some(Ok(old)) => {
+                // These may refer to code in the old library, so have to go
+                // before it's unloaded
+                self.commands = Commands::default();
+                self.snapshots.disable();
+                self.scenes = Scenes::default();
+                self.scene_stack = SceneStack::default();
+                drop(old);
+                self.dev_console.print("Reloaded the game's code");
+            }
+            Some(Err(err)) => {
+                dev_console::report_error(format!("Failed to reload the game's code: {}", err))
+            }
+            None => {}
+        }
+    }
+
+    fn console(&mut self, input: Input) -> Console<'_, G> {
+        Console {
+            input,
+            graphics: Graphics {
+                size: Vec2::new(W, H),
+                framebuffer: &mut self.framebuffer,
+                palette: &mut self.palette,
+                alpha: None,
+                views: Vec::new(),
+            },
+            audio: Audio {
+                volume: self.settings.volume,
+            },
+            save: Save {
+                version: G::SAVE_VERSION,
+                format: G::SAVE_FORMAT,
+                migrate: G::migrate_save,
+            },
+            settings: &mut self.settings,
+            high_scores: &mut self.high_scores,
+            rng: &mut self.rng,
+            debug: &mut self.debug,
+            timing: &mut self.timing,
+            filters: &mut self.filters,
+            upscaler: &mut self.upscaler,
+            commands: &mut self.commands,
+            snapshots: &mut self.snapshots,
+            scenes: &mut self.scenes,
+            capture: &mut self.capture,
+            quit: &mut self.quit,
+            tick: self.tick,
+        }
+    }
+
+    fn tick(&mut self, game: &mut G, dt: f32, input: Input) {
+        self.snapshots.resume();
+        self.restore_tick();
+
+        if let Some((_, replay)) = &mut self.recording_replay {
+            replay.record(dt, &input.input_queue);
+        }
+
+        #[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+        let hot_tick = self.hot_reload.as_ref().map(|h| h.loaded.api.tick);
+        #[cfg(not(all(feature = "hot_reload", not(target_arch = "wasm32"))))]
+        let hot_tick: Option<fn(&mut G, f32, &mut Console<'_, G>)> = None;
+
+        // The scenes are run with the same console as the game, so they're
+        // taken out of the engine while it's lent out
+        let mut scene_stack = std::mem::take(&mut self.scene_stack);
+
+        let start = instant::Instant::now();
+        let mut console = self.console(input);
+        match hot_tick {
+            Some(tick) => tick(game, dt, &mut console),
+            None => game.tick(dt, &mut console),
+        }
+        scene_stack.tick(game, dt, &mut console);
+        self.scene_stack = scene_stack;
+        if let Some(palette) = &mut self.palette {
+            palette.advance(dt);
         }
-        #[cfg(not(target_arch = "wasm32"))]
+        self.timing.tick = start.elapsed().as_secs_f32();
+        self.tick += 1;
+        // The game may have rewound itself during its tick
+        self.restore_tick();
+
+        let recorded = match &self.recording_replay {
+            Some((_, replay)) => replay.len(),
+            None => 0,
+        };
+        if let Err(err) =
+            self.snapshots
+                .record(game, &self.rng, &self.framebuffer, self.tick, recorded)
         {
-            std::fs::File::open("save.bin")
-                .map_err(bincode::Error::from)
-                .and_then(|file| bincode::deserialize_from(file))
-                .unwrap_or_default()
+            dev_console::report_error(err);
         }
     }
 
-    pub fn write(&mut self, save: S) -> Result<(), String> {
-        #[cfg(not(target_arch = "wasm32"))]
+    /// Carry on from the tick of the snapshot that was just restored, if any,
+    /// so that the tick count and the replay being recorded match the game.
+    fn restore_tick(&mut self) {
+        let restored = match self.snapshots.take_restored() {
+            Some(restored) => restored,
+            None => return,
+        };
+        self.tick = restored.tick;
+        if let (Some(ticks), Some((_, replay))) =
+            (restored.truncate_replay, &mut self.recording_replay)
         {
-            let mut f = std::fs::File::create("save.bin").map_err(|e| e.to_string())?;
-            bincode::serialize_into(&mut f, &save).map_err(|e| e.to_string())?;
+            replay.truncate(ticks);
         }
-        Ok(())
     }
-}
 
-const W: usize = 200;
-const H: usize = 150;
+    /// Tell the game about something that happened outside of its tick.
+    /// Returns `false` if the game refused to quit.
+    fn lifecycle(&mut self, game: &mut G, event: Lifecycle) -> bool {
+        // Hooks can change the game, such as pausing it when it loses focus,
+        // so replays need to call them again
+        if let Some((_, replay)) = &mut self.recording_replay {
+            replay.interrupt(Interruption::Lifecycle(event));
+        }
+
+        #[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+        if let Some(lifecycle) = self.hot_reload.as_ref().map(|h| h.loaded.api.lifecycle) {
+            return lifecycle(game, event, &mut self.console(Input::idle()));
+        }
+
+        event.dispatch(game, &mut self.console(Input::idle()))
+    }
+
+    /// Check with the game whether it should quit, if it has asked to.
+    /// Returns `true` if it should.
+    fn should_quit(&mut self, game: &mut G) -> bool {
+        std::mem::take(&mut self.quit) && self.lifecycle(game, Lifecycle::Quit)
+    }
+
+    fn set_focused(&mut self, game: &mut G, focused: bool) {
+        self.focus_paused = G::PAUSE_ON_FOCUS_LOST && !focused;
+        match focused {
+            true => self.lifecycle(game, Lifecycle::FocusGained),
+            false => self.lifecycle(game, Lifecycle::FocusLost),
+        };
+    }
+
+    fn set_suspended(&mut self, game: &mut G, suspended: bool) {
+        if suspended == self.suspended {
+            return;
+        }
+        self.suspended = suspended;
+        match suspended {
+            true => self.lifecycle(game, Lifecycle::Suspend),
+            false => self.lifecycle(game, Lifecycle::Resume),
+        };
+    }
+
+    /// Do something to the game again that happened between ticks when a
+    /// replay was recorded.
+    fn interrupt(&mut self, game: &mut G, interruption: Interruption) {
+        match interruption {
+            Interruption::Command(line) => self.run_command(game, &line),
+            Interruption::Lifecycle(event) => {
+                self.lifecycle(game, event);
+            }
+        }
+    }
+
+    /// Run a line entered in the developer console.
+    fn run_command(&mut self, game: &mut G, line: &str) {
+        let (name, args) = match dev_console::parse(line) {
+            Some(command) => command,
+            None => return,
+        };
+
+        let result = match name {
+            "help" => {
+                for (name, help) in dev_console::BUILTINS {
+                    self.dev_console.print(format!("{} - {}", name, help));
+                }
+                let names = self.commands.names().collect::<Vec<_>>().join(" ");
+                if !names.is_empty() {
+                    self.dev_console.print(names);
+                }
+                Ok(())
+            }
+            "fps" => {
+                self.dev_console
+                    .print(format!("{:.1} fps", self.timing.fps()));
+                Ok(())
+            }
+            "timescale" => match args.is_empty() {
+                true => {
+                    self.dev_console
+                        .print(format!("{}", self.time_control.scale()));
+                    Ok(())
+                }
+                false => args.get(0).map(|scale| self.time_control.set_scale(scale)),
+            },
+            "screenshot" => {
+                self.capture.screenshot = Some(false);
+                Ok(())
+            }
+            "quit" => {
+                self.quit = true;
+                Ok(())
+            }
+            name => {
+                // The built-in commands leave the game alone, but its own
+                // commands can change it, so replays need to run them again
+                if let Some((_, replay)) = &mut self.recording_replay {
+                    replay.interrupt(Interruption::Command(line.to_string()));
+                }
+                self.commands
+                    .run(game, name, &args)
+                    .unwrap_or_else(|| Err(format!("unknown command: {}", name)))
+            }
+        };
+
+        if let Err(err) = result {
+            self.dev_console.print_error(err);
+        }
+    }
+
+    /// Clean up once the game has stopped running.
+    fn exit(&mut self) {
+        if let Some((path, replay)) = &self.recording_replay {
+            if let Err(err) = replay.write(path) {
+                dev_console::report_error(format!("Failed to write replay to {}: {}", path, err));
+            }
+        }
+    }
+}
 
 fn run_with<G: Game>() {
+    let options = Options::from_args();
+
+    let replay = options
+        .replay
+        .as_deref()
+        .and_then(|path| match Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                dev_console::report_error(format!("Failed to load replay from {}: {}", path, err));
+                None
+            }
+        });
+
+    // Replays must use the seed they were recorded with to play back the same
+    let seed = match &replay {
+        Some(replay) => replay.seed,
+        None => options.seed.or(G::SEED).unwrap_or_else(unix_time_ms),
+    };
+
+    let mut engine = Engine::<G>::new(&options, seed);
+    let mut replay = replay.map(Replay::play);
+
+    if options.headless {
+        let mut game = engine.init();
+        if let Some(replay) = &mut replay {
+            while let Some((interruptions, dt, input)) = replay.next() {
+                for interruption in interruptions {
+                    engine.interrupt(&mut game, interruption);
+                }
+                engine.tick(&mut game, dt, input);
+                if engine.should_quit(&mut game) {
+                    break;
+                }
+            }
+        }
+        engine.exit();
+        return;
+    }
+
     let event_loop = EventLoop::new();
 
     let window = WindowBuilder::new()
@@ -118,13 +692,24 @@ fn run_with<G: Game>() {
         .build(&event_loop)
         .unwrap();
 
-    let scale = (4.0 * window.scale_factor()).max(1.0) as usize;
+    let scale = (engine.settings.window_scale as f64 * window.scale_factor()).max(1.0) as usize;
 
     window.set_inner_size(winit::dpi::PhysicalSize::new(
         (W * scale) as f64,
         (H * scale) as f64,
     ));
 
+    if let Some(millihertz) = window
+        .current_monitor()
+        .and_then(|monitor| monitor.refresh_rate_millihertz())
+    {
+        engine.timing.refresh_rate = millihertz as f32 / 1000.0;
+    }
+
+    if engine.settings.fullscreen {
+        window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+
     #[cfg(target_arch = "wasm32")]
     {
         use js_sys::{Array, Object};
@@ -143,67 +728,175 @@ fn run_with<G: Game>() {
     let context = unsafe { softbuffer::Context::new(&window) }.unwrap();
     let mut surface = unsafe { softbuffer::Surface::new(&context, &window) }.unwrap();
 
-    let window_size = window.inner_size();
-    let mut framebuffer = vec![0; W * H];
     let mut framebuffer_actual = vec![0; W * H * scale * scale];
+    // The size of the window that the upscaled buffer was last drawn for
+    let mut actual_size = Vec2::new(W, H) * scale;
+    let mut framebuffer_overlay = vec![0; W * H];
+    // The framebuffer's colours, when it holds palette indices
+    let mut framebuffer_colors = vec![0; W * H];
+    // How the last frame was upscaled, or `None` if it was drawn with filters
+    let mut drawn_with = Some(G::UPSCALER);
     let _flag = false;
 
     let mut time = instant::Instant::now();
 
     let mut input_helper = WinitInputHelper::new();
     let mut input_queue = Vec::new();
-    let mut game_input = Input {
-        input_queue: Vec::new(),
-        input_helper: input_helper.clone(),
-    };
+    // Whether the input gathered so far has been given to the game
+    let mut input_used = true;
+    // Stands in for the player's input while they're using the developer
+    // console
+    let idle_input_helper = WinitInputHelper::new();
 
-    let mut game = G::init(&mut Console {
-        input: game_input.clone(),
-        graphics: Graphics {
-            size: Vec2::new(W, H),
-            framebuffer: &mut framebuffer,
-        },
-        audio: Audio,
-        save: Save {
-            phantom: PhantomData,
-        },
-        tick: 0,
-    });
+    let mut game = engine.init();
+
+    // Browsers don't tell winit when the game's tab is hidden, so listen for it
+    #[cfg(target_arch = "wasm32")]
+    let hidden = {
+        use std::{cell::Cell, rc::Rc};
+        use wasm_bindgen::JsCast;
+
+        let hidden = Rc::new(Cell::new(false));
+        let document = web_sys::window().unwrap().document().unwrap();
+        let on_visibility_change = {
+            let hidden = hidden.clone();
+            let document = document.clone();
+            Closure::wrap(Box::new(move || hidden.set(document.hidden())) as Box<dyn FnMut()>)
+        };
+        document
+            .add_event_listener_with_callback(
+                "visibilitychange",
+                on_visibility_change.as_ref().unchecked_ref(),
+            )
+            .unwrap();
+        on_visibility_change.forget();
+        hidden
+    };
 
-    let mut tick = 0;
     event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
+        *control_flow = engine.timing.control_flow();
+
+        #[cfg(target_arch = "wasm32")]
+        engine.set_suspended(&mut game, hidden.get());
+
+        // Push any input into the input queue, for the game and replays
+        if let Event::WindowEvent { event, .. } = &event {
+            input_queue.extend(InputEvent::from_window_event(event));
+        }
 
         match event {
             Event::RedrawRequested(window_id) if window_id == window.id() => {
+                // Draw the debug overlays on a copy of the framebuffer, so that
+                // they don't end up in the game's next frame or in captures
+                let time_label = engine
+                    .snapshots
+                    .label()
+                    .or_else(|| engine.time_control.label());
+                let framebuffer = match engine.palette.is_some() {
+                    true => &framebuffer_colors,
+                    false => &engine.framebuffer,
+                };
+                let framebuffer = match engine.debug.is_visible()
+                    || time_label.is_some()
+                    || engine.dev_console.is_open()
+                {
+                    true => {
+                        framebuffer_overlay.copy_from_slice(framebuffer);
+                        let mut graphics = Graphics {
+                            size: Vec2::new(W, H),
+                            framebuffer: &mut framebuffer_overlay,
+                            palette: &mut None,
+                            alpha: None,
+                            views: Vec::new(),
+                        };
+                        if engine.debug.is_visible() {
+                            engine
+                                .debug
+                                .draw(&mut graphics, engine.tick, &engine.timing);
+                        }
+                        if let Some(label) = time_label {
+                            let size = Graphics::text_size(&label);
+                            let pos = Vec2::new(W as i64 - size.x - 2, 2);
+                            graphics.draw_rect(
+                                Rect::new(pos.x - 2, pos.y - 2, size.x + 4, size.y + 4).as_(),
+                                0x000000,
+                                true,
+                            );
+                            graphics.draw_text(&label, pos, 0xFFFF00);
+                        }
+                        if engine.dev_console.is_open() {
+                            engine.dev_console.draw(&mut graphics);
+                        }
+                        &framebuffer_overlay
+                    }
+                    false => framebuffer,
+                };
+
+                let start = instant::Instant::now();
                 let sz = window.inner_size();
                 let (width, height) = (sz.width as usize, sz.height as usize);
 
-                // Resize the off-screen buffer if the window size has changed
-                // if framebuffer_actual.len() != width * height {
-                //     framebuffer_actual.resize(width * height, 0);
-                // }
+                // Resize the off-screen buffer if the window size has changed,
+                // such as when going fullscreen. The old image no longer lines
+                // up, and would show through the new letterbox, so start over
+                if actual_size != Vec2::new(width, height) {
+                    framebuffer_actual.resize(width * height, 0);
+                    framebuffer_actual.fill(0);
+                    actual_size = Vec2::new(width, height);
+                }
 
-                for j in 0..H {
-                    for j2 in 0..scale {
-                        for i in 0..W {
-                            let idx = ((j * scale + j2) * W + i) * scale;
-                            framebuffer_actual[idx..idx + scale].fill(framebuffer[j * W + i]);
-                        }
+                // Filters do their own upscaling
+                let (src_size, dst_size) = (Vec2::new(W, H), Vec2::new(width, height));
+                let upscaler = match engine.settings.filters && engine.filters.is_enabled() {
+                    true => None,
+                    false => Some(engine.upscaler),
+                };
+                // Switching how the image is drawn can leave parts of the last
+                // one around the new one
+                if upscaler != drawn_with {
+                    framebuffer_actual.fill(0);
+                    drawn_with = upscaler;
+                }
+                match upscaler {
+                    Some(upscaler) => {
+                        upscaler.upscale(framebuffer, src_size, &mut framebuffer_actual, dst_size)
                     }
+                    None => engine.filters.upscale(
+                        framebuffer,
+                        src_size,
+                        &mut framebuffer_actual,
+                        dst_size,
+                        fit_scale(width, height),
+                    ),
                 }
 
+                let upscaled = instant::Instant::now();
+                engine.timing.upscale = upscaled.duration_since(start).as_secs_f32();
+
                 // Blit the offscreen buffer to the window's client area
-                if framebuffer_actual.len() == width * height {
-                    surface.set_buffer(&framebuffer_actual, sz.width as u16, sz.height as u16);
-                }
+                surface.set_buffer(&framebuffer_actual, sz.width as u16, sz.height as u16);
+                engine.timing.blit = upscaled.elapsed().as_secs_f32();
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 window_id,
             } if window_id == window.id() => {
-                *control_flow = ControlFlow::Exit;
+                // The game might want to save first, or to keep running
+                let quit = engine.lifecycle(&mut game, Lifecycle::Quit);
+                if quit {
+                    engine.exit();
+                    *control_flow = ControlFlow::Exit;
+                }
             }
+            Event::WindowEvent {
+                event: WindowEvent::Focused(focused),
+                window_id,
+            } if window_id == window.id() => {
+                engine.set_focused(&mut game, focused);
+            }
+            // Mobile platforms suspend the game when it's put in the background
+            Event::Suspended => engine.set_suspended(&mut game, true),
+            Event::Resumed => engine.set_suspended(&mut game, false),
             // Event::WindowEvent {
             //     event:
             //         WindowEvent::KeyboardInput {
@@ -221,51 +914,169 @@ fn run_with<G: Game>() {
             //     flag = !flag;
             //     window.request_redraw();
             // }
-            // Push any keyboard input events into the input queue
-            Event::WindowEvent {
-                event: WindowEvent::KeyboardInput { input, .. },
-                ..
-            } => {
-                input_queue.push(InputEvent::KeyboardInput(input));
-            }
-            // Push any mouse movement events into the input queue
-            Event::WindowEvent {
-                event: WindowEvent::CursorMoved { position, .. },
-                ..
-            } => {
-                input_queue.push(InputEvent::CursorMoved(position));
-            }
             // Event::MainEventsCleared => {
 
             // }
             _ => {}
         }
 
-        if input_helper.update(&event) {
+        // Input is gathered until the next frame is due, rather than starting
+        // afresh every time the event loop wakes up, so that nothing pressed
+        // between frames is missed
+        if let Event::NewEvents(_) = event {
+            if !input_used {
+                return;
+            }
+            input_used = false;
+        }
+
+        if input_helper.update(&event) && engine.timing.is_due(instant::Instant::now()) {
+            input_used = true;
             let new_time = instant::Instant::now();
+            engine.timing.frame_started(new_time);
+            engine
+                .timing
+                .record_frame(new_time.duration_since(time).as_secs_f32());
+
+            #[cfg(all(feature = "hot_reload", not(target_arch = "wasm32")))]
+            engine.reload(&mut game);
+
+            let live_input = Input {
+                input_queue: input_queue.clone(),
+                input_helper: input_helper.clone(),
+            };
+
+            engine.dev_console.print_reported();
+            let names = engine
+                .commands
+                .names()
+                .chain(dev_console::BUILTINS.iter().map(|(name, _)| *name));
+            if let Some(line) =
+                engine
+                    .dev_console
+                    .handle_input(&live_input, &engine.settings, names)
+            {
+                engine.run_command(&mut game, &line);
+            }
+
+            match engine.snapshots.handle_input(
+                &input_helper,
+                &engine.settings,
+                &mut game,
+                &mut engine.rng,
+                &mut engine.framebuffer,
+            ) {
+                Ok(true) => engine.time_control.pause(),
+                Ok(false) => {}
+                Err(err) => dev_console::report_error(err),
+            }
+            engine
+                .time_control
+                .handle_input(&input_helper, &engine.settings);
+
+            let advance = match engine.suspended || engine.focus_paused {
+                true => None,
+                false => engine.time_control.advance(),
+            };
+            let ticked = advance.map(|time_scale| {
+                // While a replay is playing, its input is used instead of the
+                // player's. Once it has finished, the player takes over. The
+                // game doesn't see what's typed into the developer console.
+                let (dt, input) = match replay.as_mut().and_then(Player::next) {
+                    Some((interruptions, dt, input)) => {
+                        for interruption in interruptions {
+                            engine.interrupt(&mut game, interruption);
+                        }
+                        (dt, input)
+                    }
+                    None => {
+                        replay = None;
+                        let input = match engine.dev_console.is_open() {
+                            true => Input {
+                                input_queue: Vec::new(),
+                                input_helper: idle_input_helper.clone(),
+                            },
+                            false => live_input,
+                        };
+                        (
+                            new_time.duration_since(time).as_secs_f32() * time_scale,
+                            input,
+                        )
+                    }
+                };
+
+                engine.tick(&mut game, dt, input);
+                dt
+            });
 
-            game.tick(
-                new_time.duration_since(time).as_secs_f32(),
-                &mut Console {
-                    input: Input {
-                        input_queue: input_queue.clone(),
-                        input_helper: input_helper.clone(),
-                    },
-                    graphics: Graphics {
-                        size: Vec2::new(W, H),
-                        framebuffer: &mut framebuffer,
-                    },
-                    audio: Audio,
-                    save: Save {
-                        phantom: PhantomData,
-                    },
-                    tick,
-                },
-            );
-            tick += 1;
+            let Engine {
+                framebuffer,
+                palette,
+                settings,
+                debug,
+                capture,
+                ..
+            } = &mut engine;
+
+            // Look the palette indices up once per frame, for both the window
+            // and captures
+            let framebuffer = match palette {
+                Some(palette) => {
+                    palette.resolve(framebuffer, &mut framebuffer_colors);
+                    &framebuffer_colors
+                }
+                None => framebuffer,
+            };
+
+            if input_helper.key_pressed(settings.key("debug_overlay", VirtualKeyCode::F3)) {
+                debug.toggle();
+            }
+
+            if input_helper.key_pressed(settings.key("filters", VirtualKeyCode::F9)) {
+                settings.filters = !settings.filters;
+                if let Err(err) = settings.save() {
+                    dev_console::report_error(format!("Failed to save settings: {}", err));
+                }
+            }
+
+            if input_helper.key_pressed(settings.key("screenshot", VirtualKeyCode::F12)) {
+                capture.screenshot = Some(input_helper.held_shift());
+            }
+            if let Some(upscaled) = capture.screenshot.take() {
+                let sz = window.inner_size();
+                let scale = match upscaled {
+                    true => fit_scale(sz.width as usize, sz.height as usize),
+                    false => 1,
+                };
+                if let Err(err) = capture::screenshot(framebuffer, Vec2::new(W, H), scale) {
+                    dev_console::report_error(err);
+                }
+            }
+
+            if input_helper.key_pressed(settings.key("record", VirtualKeyCode::F10)) {
+                match capture.recording.take() {
+                    Some(recording) => recording.finish(),
+                    None => {
+                        capture.recording = Some(Recording::new(
+                            RecordingFormat::Gif,
+                            capture::DEFAULT_RECORDING_SECONDS,
+                            Vec2::new(W, H),
+                        ))
+                    }
+                }
+            }
+            if let (Some(recording), Some(dt)) = (&mut capture.recording, ticked) {
+                recording.push(framebuffer, dt);
+            }
 
             // Reset the input queue
-            game_input.input_queue.clear();
+            input_queue.clear();
+
+            if engine.should_quit(&mut game) {
+                engine.exit();
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
 
             window.request_redraw();
 
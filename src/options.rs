@@ -0,0 +1,51 @@
+//! Options given to the game on the command line.
+
+use crate::dev_console::report_error;
+
+/// How the engine should run the game, set with command line arguments:
+///
+/// - `--record-replay <file>` records the session's input to a replay file.
+/// - `--replay <file>` plays a replay back before handing control to the
+///   player.
+/// - `--headless` plays the replay back without opening a window, then exits.
+/// - `--seed <number>` seeds the [`crate::Rng`] on the console.
+/// - `--uncapped` ticks the game as fast as possible, for benchmarking.
+/// - `--hot-reload <library>` runs the game's logic from a dynamic library,
+///   reloading it whenever it's rebuilt. See [`crate::hot_reload!`].
+#[derive(Debug, Default)]
+pub(crate) struct Options {
+    pub(crate) record_replay: Option<String>,
+    pub(crate) replay: Option<String>,
+    pub(crate) headless: bool,
+    pub(crate) seed: Option<u64>,
+    pub(crate) uncapped: bool,
+    pub(crate) hot_reload: Option<String>,
+}
+
+impl Options {
+    pub(crate) fn from_args() -> Self {
+        let mut options = Self::default();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut args = std::env::args().skip(1);
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--record-replay" => options.record_replay = args.next(),
+                    "--replay" => options.replay = args.next(),
+                    "--headless" => options.headless = true,
+                    "--seed" => options.seed = args.next().and_then(|seed| seed.parse().ok()),
+                    "--uncapped" => options.uncapped = true,
+                    "--hot-reload" => options.hot_reload = args.next(),
+                    _ => report_error(format!("Ignoring unknown argument {}", arg)),
+                }
+            }
+        }
+
+        if options.hot_reload.is_some() && !cfg!(feature = "hot_reload") {
+            report_error("Hot reloading needs the hot_reload feature");
+        }
+
+        options
+    }
+}
@@ -0,0 +1,454 @@
+//! Indexed colour, for games with a fixed palette.
+
+use std::{collections::HashSet, fmt, ops::RangeInclusive, path::Path};
+
+use crate::Color;
+
+/// The most colours a palette can have, since indices are bytes.
+const MAX_COLORS: usize = 256;
+
+/// A fixed set of colours that the framebuffer's pixels index into.
+///
+/// While a palette is set with [`crate::Graphics::set_palette`], the game
+/// draws palette indices rather than colours: everything that takes a colour,
+/// such as [`crate::Graphics::clear`] and [`crate::Graphics::draw_text`],
+/// takes a [`Color::index`] instead, and sprites are drawn with the nearest colours in
+/// the palette. The indices are looked up in the palette as the frame is
+/// shown, so changing the palette recolours everything on screen at once,
+/// such as for a damage flash. Effects that blend colours, like
+/// [`crate::Transition::Fade`], need the full-colour framebuffer.
+///
+/// Palettes can be loaded from `.hex`, `.gpl` and image files, such as those
+/// from [Lospec](https://lospec.com/palette-list), with [`palette`].
+///
+/// # Example
+///
+/// ```ignore
+/// const WATER: RangeInclusive<u8> = 12..=15;
+///
+/// // In `init`, shimmer the water by cycling its colours four times a second
+/// let mut palette = palette!("../pico8.hex");
+/// palette.cycle(WATER, 4.0);
+/// console.graphics.set_palette(Some(palette));
+///
+/// // In `tick`, flash the player white when they're hit
+/// if let Some(palette) = console.graphics.palette_mut() {
+///     palette.set(PLAYER, if self.hurt > 0.0 { Color::WHITE } else { PLAYER_COLOR });
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    colors: Vec<Color>,
+    cycles: Vec<Cycle>,
+}
+
+/// A range of colours that moves along the palette over time.
+#[derive(Debug, Clone, PartialEq)]
+struct Cycle {
+    range: RangeInclusive<u8>,
+    /// How many places the colours move each second.
+    rate: f32,
+    /// How far the colours have moved so far.
+    offset: f32,
+}
+
+impl Palette {
+    /// Create a palette from a list of colours. Anything past the 256th colour
+    /// is left out.
+    pub fn new<C: Into<Color>>(colors: impl IntoIterator<Item = C>) -> Self {
+        Self {
+            colors: colors
+                .into_iter()
+                .take(MAX_COLORS)
+                .map(|color| color.into().with_alpha(255))
+                .collect(),
+            cycles: Vec::new(),
+        }
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// The colour at an index, or black if the palette doesn't have that many
+    /// colours. This doesn't account for any cycles.
+    pub fn get(&self, index: u8) -> Color {
+        self.colors
+            .get(index as usize)
+            .copied()
+            .unwrap_or(Color::BLACK)
+    }
+
+    /// Change the colour at an index, adding black colours up to it if the
+    /// palette doesn't have that many.
+    pub fn set(&mut self, index: u8, color: impl Into<Color>) {
+        let index = index as usize;
+        if index >= self.colors.len() {
+            self.colors.resize(index + 1, Color::BLACK);
+        }
+        self.colors[index] = color.into().with_alpha(255);
+    }
+
+    /// Swap the colours at two indices.
+    pub fn swap(&mut self, a: u8, b: u8) {
+        let (a, b) = (a as usize, b as usize);
+        if a < self.colors.len() && b < self.colors.len() {
+            self.colors.swap(a, b);
+        }
+    }
+
+    /// Move the colours in a range of indices along by `steps`, towards the
+    /// end of the range, wrapping the last colours around to the start.
+    /// Negative steps move them the other way. Indices past the end of the
+    /// palette are left out, and reversed ranges are ignored.
+    pub fn rotate(&mut self, range: RangeInclusive<u8>, steps: isize) {
+        if let Some(colors) = self.range_mut(&range) {
+            let steps = steps.rem_euclid(colors.len() as isize) as usize;
+            colors.rotate_right(steps);
+        }
+    }
+
+    /// Keep rotating the colours in a range of indices as the game runs,
+    /// moving them `rate` places every second, like [`Palette::rotate`]. The
+    /// colours themselves don't change, only which index shows which.
+    /// Reversed ranges are ignored.
+    pub fn cycle(&mut self, range: RangeInclusive<u8>, rate: f32) {
+        if range.is_empty() {
+            return;
+        }
+        self.cycles.push(Cycle {
+            range,
+            rate,
+            offset: 0.0,
+        });
+    }
+
+    /// Stop all the cycles started with [`Palette::cycle`].
+    pub fn stop_cycles(&mut self) {
+        self.cycles.clear();
+    }
+
+    /// The index of the colour in the palette closest to `color`.
+    pub fn nearest(&self, color: impl Into<Color>) -> u8 {
+        let color = color.into();
+        let distance = |other: &Color| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(color.r, other.r) + d(color.g, other.g) + d(color.b, other.b)
+        };
+        self.colors
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, other)| distance(other))
+            .map(|(index, _)| index as u8)
+            .unwrap_or(0)
+    }
+
+    /// Read a palette file, picking its format from the extension of `name`:
+    /// see [`Palette::from_hex`], [`Palette::from_gpl`] and
+    /// [`Palette::from_image`].
+    pub fn decode(name: &str, bytes: &[u8]) -> Result<Self, PaletteError> {
+        let extension = Path::new(name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        let text = || String::from_utf8_lossy(bytes);
+        match extension.as_deref() {
+            Some("hex") => Self::from_hex(&text()),
+            Some("gpl") => Self::from_gpl(&text()),
+            Some("png" | "gif" | "bmp") => Self::from_image(bytes),
+            _ => Err(PaletteError::UnknownFormat(name.to_string())),
+        }
+    }
+
+    /// Load a palette file from disk, as [`Palette::decode`]. On the web, use
+    /// [`palette`] to embed the file in the game instead.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PaletteError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|err| PaletteError::Io(err.to_string()))?;
+        Self::decode(&path.to_string_lossy(), &bytes)
+    }
+
+    /// Parse a palette from a `.hex` file, which has one colour per line
+    /// written as `RRGGBB`, optionally starting with a `#`.
+    pub fn from_hex(text: &str) -> Result<Self, PaletteError> {
+        let colors = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(i, line)| {
+                Color::parse_hex(line)
+                    .map(|color| color.with_alpha(255))
+                    .ok_or_else(|| {
+                        PaletteError::Invalid(format!("line {}: {:?} isn't a colour", i + 1, line))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::checked(colors)
+    }
+
+    /// Parse a palette from a GIMP `.gpl` file.
+    pub fn from_gpl(text: &str) -> Result<Self, PaletteError> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.trim() == "GIMP Palette" => {}
+            _ => {
+                return Err(PaletteError::Invalid(
+                    "missing GIMP Palette header".to_string(),
+                ))
+            }
+        }
+
+        let colors = lines
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| {
+                !(line.is_empty()
+                    || line.starts_with('#')
+                    || line.starts_with("Name:")
+                    || line.starts_with("Columns:"))
+            })
+            .map(|(i, line)| {
+                let channels = line
+                    .split_whitespace()
+                    .take(3)
+                    .map(|channel| channel.parse::<u8>().ok())
+                    .collect::<Option<Vec<_>>>();
+                match channels.as_deref() {
+                    Some(&[r, g, b]) => Ok(Color::rgb(r, g, b)),
+                    _ => Err(PaletteError::Invalid(format!(
+                        "line {}: {:?} isn't a colour",
+                        i + 1,
+                        line
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::checked(colors)
+    }
+
+    /// Take a palette from the colours in an image, in the order they first
+    /// appear reading left to right, top to bottom. Transparent pixels are
+    /// skipped, so swatches can be laid out with gaps between them.
+    pub fn from_image(bytes: &[u8]) -> Result<Self, PaletteError> {
+        let img = image::load_from_memory(bytes)
+            .map_err(|err| PaletteError::Invalid(err.to_string()))?
+            .into_rgba8();
+        let mut seen = HashSet::new();
+        let colors = img
+            .pixels()
+            .map(|p| Color::from(*p))
+            .filter(|color| color.a != 0)
+            .map(|color| color.with_alpha(255))
+            .filter(|color| seen.insert(*color))
+            .collect();
+        Self::checked(colors)
+    }
+
+    fn checked(colors: Vec<Color>) -> Result<Self, PaletteError> {
+        match colors.len() {
+            0 => Err(PaletteError::Invalid("there are no colours".to_string())),
+            len if len > MAX_COLORS => Err(PaletteError::TooManyColors(len)),
+            _ => Ok(Self::new(colors)),
+        }
+    }
+
+    /// The colours in a range of indices, or `None` if none of them are in
+    /// the palette.
+    fn range_mut(&mut self, range: &RangeInclusive<u8>) -> Option<&mut [Color]> {
+        let end = (*range.end() as usize).min(self.colors.len().checked_sub(1)?);
+        self.colors
+            .get_mut(*range.start() as usize..=end)
+            .filter(|colors| !colors.is_empty())
+    }
+
+    /// Move the cycles on by `dt` seconds.
+    pub(crate) fn advance(&mut self, dt: f32) {
+        for cycle in &mut self.cycles {
+            let len = cycle.range.len() as f32;
+            cycle.offset = (cycle.offset + cycle.rate * dt).rem_euclid(len.max(1.0));
+        }
+    }
+
+    /// Look up the colour of each index in `indices`.
+    pub(crate) fn resolve(&self, indices: &[u32], colors: &mut [u32]) {
+        let lookup = self.lookup();
+        for (color, index) in colors.iter_mut().zip(indices) {
+            *color = lookup[*index as usize % MAX_COLORS];
+        }
+    }
+
+    /// The pixel shown for every index, with the cycles applied.
+    pub(crate) fn lookup(&self) -> [u32; MAX_COLORS] {
+        let mut lookup = [0x000000; MAX_COLORS];
+        for (pixel, color) in lookup.iter_mut().zip(&self.colors) {
+            *pixel = color.to_pixel();
+        }
+        for cycle in &self.cycles {
+            let range = *cycle.range.start() as usize..=*cycle.range.end() as usize;
+            if let Some(colors) = lookup.get_mut(range).filter(|colors| !colors.is_empty()) {
+                colors.rotate_right(cycle.offset as usize % colors.len());
+            }
+        }
+        lookup
+    }
+}
+
+/// The reasons that reading a [`Palette`] can fail.
+#[derive(Debug)]
+pub enum PaletteError {
+    /// The file isn't in any of the formats that palettes can be read from.
+    UnknownFormat(String),
+    /// The file couldn't be parsed.
+    Invalid(String),
+    /// The file has more colours than a palette can hold.
+    TooManyColors(usize),
+    /// The file couldn't be read.
+    Io(String),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat(name) => write!(f, "{} isn't a palette file", name),
+            Self::Invalid(err) => write!(f, "palette is invalid: {}", err),
+            Self::TooManyColors(len) => write!(
+                f,
+                "palette has {} colours, but only {} are supported",
+                len, MAX_COLORS
+            ),
+            Self::Io(err) => write!(f, "could not read palette: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+/// A macro that can be used to load a palette into the game at compilation
+/// time, from any of the formats that [`Palette::decode`] reads.
+///
+/// # Example
+///
+/// ```ignore
+/// console.graphics.set_palette(Some(palette!("../pico8.hex")));
+/// ```
+#[macro_export]
+macro_rules! palette {
+    ($s:literal $(,)?) => {
+        $crate::Palette::decode($s, ::std::include_bytes!($s)).unwrap()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(levels: u8) -> Palette {
+        Palette::new((0..levels).map(|i| Color::rgb(i, i, i)))
+    }
+
+    #[test]
+    fn parses_hex() {
+        let palette = Palette::from_hex("#000000\n\nff8000\n  FFFFFF  \n").unwrap();
+        assert_eq!(
+            palette.colors(),
+            [Color::BLACK, Color::ORANGE, Color::WHITE]
+        );
+        assert!(matches!(
+            Palette::from_hex("000000\nnope"),
+            Err(PaletteError::Invalid(_))
+        ));
+        assert!(matches!(
+            Palette::from_hex(""),
+            Err(PaletteError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn parses_gpl() {
+        let text = "GIMP Palette\nName: Test\nColumns: 2\n# A comment\n255 0 0\tRed\n0 0 255\n";
+        let palette = Palette::from_gpl(text).unwrap();
+        assert_eq!(palette.colors(), [Color::RED, Color::BLUE]);
+        assert!(Palette::from_gpl("255 0 0\n").is_err());
+        assert!(Palette::from_gpl("GIMP Palette\n255 0\n").is_err());
+    }
+
+    #[test]
+    fn decodes_by_extension() {
+        assert_eq!(Palette::decode("a.HEX", b"ff0000").unwrap().len(), 1);
+        assert!(matches!(
+            Palette::decode("a.txt", b"ff0000"),
+            Err(PaletteError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn too_many_colours() {
+        let text: String = (0..300).map(|i| format!("{:06x}\n", i)).collect();
+        assert!(matches!(
+            Palette::from_hex(&text),
+            Err(PaletteError::TooManyColors(300))
+        ));
+    }
+
+    #[test]
+    fn nearest() {
+        let palette = Palette::new([Color::BLACK, Color::RED, Color::WHITE]);
+        assert_eq!(palette.nearest(Color::rgb(200, 10, 10)), 1);
+        assert_eq!(palette.nearest(Color::rgb(200, 200, 200)), 2);
+        assert_eq!(Palette::new(Vec::<Color>::new()).nearest(Color::RED), 0);
+    }
+
+    #[test]
+    fn rotate() {
+        let mut palette = gray(4);
+        palette.rotate(1..=3, 1);
+        assert_eq!(
+            palette.colors().iter().map(|c| c.r).collect::<Vec<_>>(),
+            [0, 3, 1, 2]
+        );
+        palette.rotate(1..=3, -1);
+        assert_eq!(palette, gray(4));
+    }
+
+    #[test]
+    fn rotate_outside_palette() {
+        let mut palette = gray(4);
+        palette.rotate(4..=7, 1);
+        palette.rotate(6..=7, 1);
+        #[allow(clippy::reversed_empty_ranges)]
+        palette.rotate(3..=1, 1);
+        assert_eq!(palette, gray(4));
+        Palette::new(Vec::<Color>::new()).rotate(0..=3, 1);
+    }
+
+    #[test]
+    fn cycles() {
+        let mut palette = gray(4);
+        palette.cycle(0..=1, 2.0);
+        palette.advance(0.5);
+        let mut colors = [0; 4];
+        palette.resolve(&[0, 1, 2, 3], &mut colors);
+        assert_eq!(colors, [1, 0, 2, 3].map(|i| Color::rgb(i, i, i).to_pixel()));
+        // The colours themselves aren't moved
+        assert_eq!(palette.get(0), Color::BLACK);
+    }
+
+    #[test]
+    fn cycles_outside_palette() {
+        let mut palette = gray(4);
+        #[allow(clippy::reversed_empty_ranges)]
+        palette.cycle(5..=3, 1.0);
+        palette.cycle(4..=7, 1.0);
+        palette.advance(1.5);
+        let mut colors = [0; 2];
+        palette.resolve(&[3, 6], &mut colors);
+        assert_eq!(colors, [Color::rgb(3, 3, 3).to_pixel(), 0x000000]);
+    }
+}
@@ -0,0 +1,279 @@
+//! Recording the input a game receives, so that a session can be played back
+//! exactly as it happened.
+
+use serde_derive::{Deserialize, Serialize};
+use winit::dpi::PhysicalSize;
+use winit::event::{DeviceId, Event, StartCause, WindowEvent};
+use winit::window::WindowId;
+use winit_input_helper::WinitInputHelper;
+
+use crate::{input::InputEvent, prelude::Input, storage, Lifecycle, SaveError};
+
+/// Bytes written at the start of every replay, ahead of the replay version.
+const MAGIC: &[u8; 4] = b"MJR\0";
+
+/// The version of the replay format written by this build of the engine.
+const VERSION: u32 = 2;
+
+/// Every [`InputEvent`] and time step given to [`crate::Game::tick`] over a
+/// session, and everything done to the game between ticks, along with the seed
+/// of the console's [`crate::Rng`].
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Replay {
+    pub(crate) seed: u64,
+    ticks: Vec<ReplayTick>,
+    /// What has happened since the last tick was recorded.
+    #[serde(skip)]
+    interruptions: Vec<Interruption>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayTick {
+    /// What happened to the game before this tick.
+    interruptions: Vec<Interruption>,
+    dt: f32,
+    events: Vec<InputEvent>,
+}
+
+/// Something that changed the game outside of its tick, which has to happen
+/// again at the same point for a replay to play back the same.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Interruption {
+    /// A line entered in the developer console to run one of the game's own
+    /// commands.
+    Command(String),
+    /// One of the game's hooks, such as [`crate::Game::on_focus_lost`].
+    Lifecycle(Lifecycle),
+}
+
+impl Replay {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ticks: Vec::new(),
+            interruptions: Vec::new(),
+        }
+    }
+
+    pub(crate) fn load(path: &str) -> Result<Self, SaveError> {
+        let bytes = storage::load(path)?;
+        let rest = bytes
+            .strip_prefix(MAGIC)
+            .filter(|rest| rest.len() >= 4)
+            .ok_or_else(|| SaveError::Corrupt("not a replay".to_string()))?;
+        let (version, payload) = rest.split_at(4);
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+
+        if version > VERSION {
+            return Err(SaveError::TooNew {
+                version,
+                supported: VERSION,
+            });
+        } else if version < VERSION {
+            return Err(SaveError::Corrupt(format!(
+                "replays from version {} can no longer be played",
+                version
+            )));
+        }
+        bincode::deserialize(payload).map_err(SaveError::corrupt)
+    }
+
+    pub(crate) fn write(&self, path: &str) -> Result<(), SaveError> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, self).map_err(SaveError::corrupt)?;
+        storage::store(path, &bytes)
+    }
+
+    /// Remember something that happened between ticks. It's played back
+    /// before the next tick that's recorded.
+    pub(crate) fn interrupt(&mut self, interruption: Interruption) {
+        self.interruptions.push(interruption);
+    }
+
+    /// Remember the input given to a tick.
+    pub(crate) fn record(&mut self, dt: f32, events: &[InputEvent]) {
+        self.ticks.push(ReplayTick {
+            interruptions: std::mem::take(&mut self.interruptions),
+            dt,
+            events: events.to_vec(),
+        });
+    }
+
+    /// The number of ticks recorded so far.
+    pub(crate) fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    /// Forget the input given after the first `ticks` ticks, such as when a
+    /// developer scrubs back to a snapshot and carries on from there.
+    pub(crate) fn truncate(&mut self, ticks: usize) {
+        self.ticks.truncate(ticks);
+    }
+
+    pub(crate) fn play(self) -> Player {
+        Player {
+            ticks: self.ticks.into_iter(),
+            input_helper: WinitInputHelper::new(),
+        }
+    }
+}
+
+/// Plays back a [`Replay`], one tick at a time.
+pub(crate) struct Player {
+    ticks: std::vec::IntoIter<ReplayTick>,
+    input_helper: WinitInputHelper,
+}
+
+impl Player {
+    /// What happened before the next tick, and its time step and input, or
+    /// `None` once the replay has finished.
+    ///
+    /// The recorded events are fed through an input helper of the replay's
+    /// own, in the same order that the event loop would, so that
+    /// [`Input`]'s key and mouse state match the original session.
+    pub(crate) fn next(&mut self) -> Option<(Vec<Interruption>, f32, Input)> {
+        let tick = self.ticks.next()?;
+
+        self.input_helper
+            .update::<()>(&Event::NewEvents(StartCause::Poll));
+        for event in &tick.events {
+            let mut new_inner_size = PhysicalSize::default();
+            self.input_helper
+                .update::<()>(&to_winit(event, &mut new_inner_size));
+        }
+        self.input_helper.update::<()>(&Event::MainEventsCleared);
+
+        Some((
+            tick.interruptions,
+            tick.dt,
+            Input {
+                input_queue: tick.events,
+                input_helper: self.input_helper.clone(),
+            },
+        ))
+    }
+}
+
+/// Turn a recorded event back into the window event it came from.
+/// `new_inner_size` is only borrowed by [`WindowEvent::ScaleFactorChanged`].
+#[allow(deprecated)]
+fn to_winit<'a>(event: &InputEvent, new_inner_size: &'a mut PhysicalSize<u32>) -> Event<'a, ()> {
+    // Safety: the ids are only seen by the input helper, and are never passed
+    // back to the platform
+    let window_id = unsafe { WindowId::dummy() };
+    let device_id = unsafe { DeviceId::dummy() };
+
+    let event = match *event {
+        InputEvent::KeyboardInput(input) => WindowEvent::KeyboardInput {
+            device_id,
+            input,
+            is_synthetic: false,
+        },
+        InputEvent::CursorMoved(position) => WindowEvent::CursorMoved {
+            device_id,
+            position,
+            modifiers: Default::default(),
+        },
+        InputEvent::MouseInput { state, button } => WindowEvent::MouseInput {
+            device_id,
+            state,
+            button,
+            modifiers: Default::default(),
+        },
+        InputEvent::ReceivedCharacter(c) => WindowEvent::ReceivedCharacter(c),
+        InputEvent::MouseWheel { delta, phase } => WindowEvent::MouseWheel {
+            device_id,
+            delta,
+            phase,
+            modifiers: Default::default(),
+        },
+        InputEvent::ModifiersChanged(modifiers) => WindowEvent::ModifiersChanged(modifiers),
+        InputEvent::Resized(size) => WindowEvent::Resized(size),
+        InputEvent::ScaleFactorChanged {
+            scale_factor,
+            new_inner_size: size,
+        } => {
+            *new_inner_size = size;
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            }
+        }
+        InputEvent::Focused(focused) => WindowEvent::Focused(focused),
+        InputEvent::CloseRequested => WindowEvent::CloseRequested,
+        InputEvent::Destroyed => WindowEvent::Destroyed,
+    };
+
+    Event::WindowEvent { window_id, event }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::{
+        dpi::PhysicalPosition,
+        event::{ElementState, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase},
+    };
+
+    #[test]
+    fn events_round_trip() {
+        let events = [
+            InputEvent::CursorMoved(PhysicalPosition::new(1.0, 2.0)),
+            InputEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+            },
+            InputEvent::ReceivedCharacter('a'),
+            InputEvent::MouseWheel {
+                delta: MouseScrollDelta::LineDelta(0.0, -1.0),
+                phase: TouchPhase::Moved,
+            },
+            InputEvent::ModifiersChanged(ModifiersState::SHIFT),
+            InputEvent::Resized(PhysicalSize::new(640, 480)),
+            InputEvent::ScaleFactorChanged {
+                scale_factor: 2.0,
+                new_inner_size: PhysicalSize::new(1280, 960),
+            },
+            InputEvent::Focused(false),
+            InputEvent::CloseRequested,
+            InputEvent::Destroyed,
+        ];
+        for event in events {
+            let mut new_inner_size = PhysicalSize::default();
+            match to_winit(&event, &mut new_inner_size) {
+                Event::WindowEvent {
+                    event: window_event,
+                    ..
+                } => {
+                    assert_eq!(InputEvent::from_window_event(&window_event), Some(event));
+                }
+                _ => panic!("{:?} isn't a window event", event),
+            }
+        }
+    }
+
+    #[test]
+    fn interruptions_play_before_the_next_tick() {
+        let command = Interruption::Command("score 3".to_string());
+        let mut replay = Replay::new(1);
+        replay.record(0.1, &[]);
+        replay.interrupt(command.clone());
+        replay.record(0.1, &[]);
+        let mut player = replay.play();
+        assert_eq!(player.next().unwrap().0, []);
+        assert_eq!(player.next().unwrap().0, [command]);
+    }
+
+    #[test]
+    fn truncate() {
+        let mut replay = Replay::new(1);
+        for _ in 0..5 {
+            replay.record(0.1, &[InputEvent::Focused(true)]);
+        }
+        replay.truncate(3);
+        let mut player = replay.play();
+        assert_eq!((0..3).filter_map(|_| player.next()).count(), 3);
+        assert!(player.next().is_none());
+    }
+}
@@ -0,0 +1,243 @@
+use serde_derive::{Deserialize, Serialize};
+use std::ops::Range;
+use vek::Vec2;
+
+/// A fast, seedable random number generator.
+///
+/// Games should use the generator on the [`crate::Console`] rather than a
+/// global one, so that a run can be reproduced from its seed. The seed can be
+/// set with [`crate::Game::SEED`] or the `--seed <number>` command line
+/// argument, and is otherwise picked at startup. Replays remember the seed
+/// they were recorded with.
+///
+/// The generator's state can be serialized, so that it can be stored in a
+/// save and restored later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generate a random `u64`, using
+    /// [SplitMix64](https://prng.di.unimi.it/splitmix64.c).
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Generate a random number between 0 (inclusive) and 1 (exclusive).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Like [`Rng::next_f32`], with all the precision of an `f64`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Generate a random number in the given range.
+    ///
+    /// # Panics
+    ///
+    /// If the range is empty, or it's a range of floats with an infinite or
+    /// NaN end.
+    pub fn range<T: Uniform>(&mut self, range: Range<T>) -> T {
+        T::sample(self, range)
+    }
+
+    /// Generate a random number below `span`, which must not be 0, using
+    /// [Lemire's method](https://arxiv.org/abs/1805.10941) so that every
+    /// number is equally likely.
+    fn below(&mut self, span: u64) -> u64 {
+        let mut m = self.next_u64() as u128 * span as u128;
+        if (m as u64) < span {
+            // Reject the few numbers that would make some results more likely
+            let threshold = span.wrapping_neg() % span;
+            while (m as u64) < threshold {
+                m = self.next_u64() as u128 * span as u128;
+            }
+        }
+        (m >> 64) as u64
+    }
+
+    /// Returns `true` with the given probability, between 0 and 1.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Pick a random item from a slice, or `None` if it's empty.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        match items.len() {
+            0 => None,
+            len => items.get(self.range(0..len)),
+        }
+    }
+
+    /// Randomly reorder the items in a slice.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range(0..i + 1));
+        }
+    }
+
+    /// Generate a random point within a circle of the given radius, centred on
+    /// the origin. Points are evenly spread across the circle's area.
+    pub fn in_circle(&mut self, radius: f32) -> Vec2<f32> {
+        self.unit_vec2() * radius * self.next_f32().sqrt()
+    }
+
+    /// Generate a vector of length 1 pointing in a random direction.
+    pub fn unit_vec2(&mut self) -> Vec2<f32> {
+        let angle = self.next_f32() * std::f32::consts::TAU;
+        Vec2::new(angle.cos(), angle.sin())
+    }
+}
+
+/// Types that [`Rng::range`] can generate.
+pub trait Uniform: Sized {
+    fn sample(rng: &mut Rng, range: Range<Self>) -> Self;
+}
+
+macro_rules! impl_uniform_int {
+    ($($t:ty => $unsigned:ty),*) => {
+        $(impl Uniform for $t {
+            fn sample(rng: &mut Rng, range: Range<Self>) -> Self {
+                assert!(range.start < range.end, "cannot sample an empty range");
+                let span = range.end.wrapping_sub(range.start) as $unsigned as u64;
+                range.start.wrapping_add(rng.below(span) as Self)
+            }
+        })*
+    };
+}
+
+impl_uniform_int!(
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, usize => usize,
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize
+);
+
+// Interpolating between the ends, rather than scaling the span, keeps huge
+// ranges from overflowing. Rounding can still land on the end of the range, so
+// samples are clamped to the number just below it to keep it half-open
+
+macro_rules! impl_uniform_float {
+    ($($t:ty => $unit:ident),*) => {
+        $(impl Uniform for $t {
+            fn sample(rng: &mut Rng, range: Range<Self>) -> Self {
+                assert!(
+                    range.start.is_finite() && range.end.is_finite(),
+                    "cannot sample a range with an infinite or NaN end"
+                );
+                assert!(range.start < range.end, "cannot sample an empty range");
+                let t = rng.$unit();
+                let value = range.start * (1.0 - t) + range.end * t;
+                let below_end = if range.end > 0.0 {
+                    <$t>::from_bits(range.end.to_bits() - 1)
+                } else if range.end == 0.0 {
+                    -<$t>::from_bits(1)
+                } else {
+                    <$t>::from_bits(range.end.to_bits() + 1)
+                };
+                value.min(below_end).max(range.start)
+            }
+        })*
+    };
+}
+
+impl_uniform_float!(f32 => next_f32, f64 => next_f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn integer_ranges_stay_in_bounds() {
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            assert!((3..7).contains(&rng.range(3u8..7)));
+            assert!((-5..5).contains(&rng.range(-5i32..5)));
+        }
+        assert_eq!(rng.range(9u32..10), 9);
+        // The whole range of a type
+        rng.range(i64::MIN..i64::MAX);
+    }
+
+    #[test]
+    fn integer_ranges_are_even() {
+        let mut rng = Rng::new(2);
+        let mut counts = [0; 3];
+        for _ in 0..30_000 {
+            counts[rng.range(0usize..3)] += 1;
+        }
+        for count in counts {
+            assert!((9_000..11_000).contains(&count), "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn float_ranges_are_half_open() {
+        let mut rng = Rng::new(3);
+        // The span is so small that most samples round to one end or the other
+        let end = 1.0 + f32::EPSILON;
+        for _ in 0..1000 {
+            let value = rng.range(1.0f32..end);
+            assert!((1.0..end).contains(&value));
+            let value = rng.range(-1.0f64..1.0);
+            assert!((-1.0..1.0).contains(&value));
+        }
+        // Ranges ending at or below zero
+        let smallest = f32::from_bits(1);
+        assert_eq!(rng.range(-smallest..0.0), -smallest);
+        assert_eq!(rng.range(-1.0 - f64::EPSILON..-1.0), -1.0 - f64::EPSILON);
+    }
+
+    #[test]
+    fn huge_float_ranges() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let value = rng.range(f32::MIN..f32::MAX);
+            assert!((f32::MIN..f32::MAX).contains(&value));
+            let value = rng.range(f64::MAX / 2.0..f64::MAX);
+            assert!((f64::MAX / 2.0..f64::MAX).contains(&value));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn infinite_ranges_panic() {
+        Rng::new(8).range(0.0..f64::INFINITY);
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_ranges_panic() {
+        Rng::new(4).range(1.0f32..1.0);
+    }
+
+    #[test]
+    fn shuffle_keeps_every_item() {
+        let mut items: Vec<u32> = (0..20).collect();
+        Rng::new(5).shuffle(&mut items);
+        items.sort_unstable();
+        assert_eq!(items, (0..20).collect::<Vec<_>>());
+        assert_eq!(Rng::new(6).pick::<u32>(&[]), None);
+    }
+}
@@ -0,0 +1,276 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{borrow::Cow, fmt};
+
+use crate::storage;
+
+/// Bytes written at the start of every binary save, ahead of the save version.
+/// Saves without them were written before saves were versioned, and are
+/// treated as version 0.
+const MAGIC: &[u8; 4] = b"MJE\0";
+
+/// The comment at the start of every RON save, followed by the save version.
+const RON_HEADER: &str = "// MJE save version";
+
+/// A JSON save, with its version stored alongside the data so that the file is
+/// still valid JSON.
+#[derive(serde_derive::Serialize)]
+struct Versioned<'a, T> {
+    version: u32,
+    data: &'a T,
+}
+
+/// The encoding used to store a game's save data, chosen with
+/// [`crate::Game::SAVE_FORMAT`].
+///
+/// The text formats can be opened and edited by hand, which is useful for
+/// setting up test scenarios and attaching saves to bug reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// A compact binary encoding, stored in `save.bin`.
+    Bincode,
+    /// Pretty-printed JSON, stored in `save.json` as
+    /// `{"version": ..., "data": ...}`.
+    Json,
+    /// Pretty-printed [RON](https://github.com/ron-rs/ron), stored in
+    /// `save.ron` with the version in a comment on the first line.
+    Ron,
+}
+
+impl SaveFormat {
+    fn path(&self) -> &'static str {
+        match self {
+            Self::Bincode => "save.bin",
+            Self::Json => "save.json",
+            Self::Ron => "save.ron",
+        }
+    }
+
+    /// Encode a value in this format.
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, SaveError> {
+        match self {
+            Self::Bincode => bincode::serialize(value).map_err(SaveError::corrupt),
+            Self::Json => serde_json::to_vec_pretty(value).map_err(SaveError::corrupt),
+            Self::Ron => ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+                .map(String::into_bytes)
+                .map_err(SaveError::corrupt),
+        }
+    }
+
+    /// Decode a value from this format.
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, SaveError> {
+        match self {
+            Self::Bincode => bincode::deserialize(bytes).map_err(SaveError::corrupt),
+            Self::Json => serde_json::from_slice(bytes).map_err(SaveError::corrupt),
+            Self::Ron => ron::de::from_bytes(bytes).map_err(SaveError::corrupt),
+        }
+    }
+
+    /// Encode a save along with its version.
+    fn encode_versioned<T: Serialize>(
+        &self,
+        version: u32,
+        value: &T,
+    ) -> Result<Vec<u8>, SaveError> {
+        let mut bytes = Vec::new();
+        match self {
+            Self::Bincode => {
+                bytes.extend_from_slice(MAGIC);
+                bytes.extend_from_slice(&version.to_le_bytes());
+                bytes.extend(self.encode(value)?);
+            }
+            Self::Json => {
+                bytes = self.encode(&Versioned {
+                    version,
+                    data: value,
+                })?
+            }
+            Self::Ron => {
+                bytes.extend_from_slice(format!("{} {}\n", RON_HEADER, version).as_bytes());
+                bytes.extend(self.encode(value)?);
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Split a save into its version and payload.
+    fn split_version<'a>(&self, bytes: &'a [u8]) -> Result<(u32, Cow<'a, [u8]>), SaveError> {
+        match self {
+            Self::Bincode => match bytes.strip_prefix(MAGIC) {
+                Some(rest) if rest.len() >= 4 => {
+                    let (version, payload) = rest.split_at(4);
+                    let version = u32::from_le_bytes(version.try_into().unwrap());
+                    Ok((version, Cow::Borrowed(payload)))
+                }
+                Some(_) => Err(SaveError::Corrupt("truncated save header".to_string())),
+                None => Ok((0, Cow::Borrowed(bytes))),
+            },
+            Self::Json => match serde_json::from_slice(bytes).map_err(SaveError::corrupt)? {
+                Value::Object(mut fields) if fields.len() == 2 => {
+                    match (
+                        fields.get("version").and_then(Value::as_u64),
+                        fields.remove("data"),
+                    ) {
+                        (Some(version), Some(data)) => {
+                            let version = u32::try_from(version).map_err(SaveError::corrupt)?;
+                            let payload = serde_json::to_vec(&data).map_err(SaveError::corrupt)?;
+                            Ok((version, Cow::Owned(payload)))
+                        }
+                        _ => Ok((0, Cow::Borrowed(bytes))),
+                    }
+                }
+                _ => Ok((0, Cow::Borrowed(bytes))),
+            },
+            Self::Ron => {
+                let line_end = bytes
+                    .iter()
+                    .position(|b| *b == b'\n')
+                    .unwrap_or(bytes.len());
+                let header = std::str::from_utf8(&bytes[..line_end]).unwrap_or("");
+                match header.trim().strip_prefix(RON_HEADER) {
+                    Some(version) => version
+                        .trim()
+                        .parse()
+                        .map(|version| {
+                            (
+                                version,
+                                Cow::Borrowed(&bytes[(line_end + 1).min(bytes.len())..]),
+                            )
+                        })
+                        .map_err(|_| SaveError::Corrupt("invalid save header".to_string())),
+                    None => Ok((0, Cow::Borrowed(bytes))),
+                }
+            }
+        }
+    }
+}
+
+/// Persistent storage for a game's [`crate::Game::SaveData`].
+///
+/// Every save is written with the game's [`crate::Game::SAVE_VERSION`]. When
+/// an older save is read, it's handed to [`crate::Game::migrate_save`] to be
+/// upgraded, so changing the save data doesn't wipe players' progress.
+///
+/// Saves are encoded in the game's [`crate::Game::SAVE_FORMAT`].
+pub struct Save<S> {
+    pub(crate) version: u32,
+    pub(crate) format: SaveFormat,
+    pub(crate) migrate: fn(u32, &[u8]) -> Result<S, SaveError>,
+}
+
+impl<S: Default + Serialize + DeserializeOwned> Save<S> {
+    /// Read the save, upgrading it from an older version if required.
+    pub fn read(&mut self) -> Result<S, SaveError> {
+        let bytes = storage::load(self.format.path())?;
+        let (version, payload) = self.format.split_version(&bytes)?;
+
+        if version > self.version {
+            Err(SaveError::TooNew {
+                version,
+                supported: self.version,
+            })
+        } else if version == self.version {
+            self.format.decode(&payload)
+        } else {
+            (self.migrate)(version, &payload)
+        }
+    }
+
+    /// Read the save, falling back to the default save data if there is no
+    /// save or it couldn't be read.
+    pub fn read_or_default(&mut self) -> S {
+        self.read().unwrap_or_default()
+    }
+
+    pub fn write(&mut self, save: S) -> Result<(), SaveError> {
+        let bytes = self.format.encode_versioned(self.version, &save)?;
+        storage::store(self.format.path(), &bytes)
+    }
+}
+
+/// The reasons that reading or writing a [`Save`] can fail.
+#[derive(Debug)]
+pub enum SaveError {
+    /// Nothing has been saved yet.
+    Missing,
+    /// The save exists, but couldn't be decoded.
+    Corrupt(String),
+    /// The save was written by a newer version of the game than this one.
+    TooNew { version: u32, supported: u32 },
+    /// The save couldn't be accessed.
+    Io(String),
+}
+
+impl SaveError {
+    /// Wrap a decoding error, for use in [`crate::Game::migrate_save`].
+    pub fn corrupt(err: impl fmt::Display) -> Self {
+        Self::Corrupt(err.to_string())
+    }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no save data found"),
+            Self::Corrupt(err) => write!(f, "save data is corrupt: {}", err),
+            Self::TooNew { version, supported } => write!(
+                f,
+                "save data is version {}, but only versions up to {} are supported",
+                version, supported
+            ),
+            Self::Io(err) => write!(f, "could not access save data: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [SaveFormat; 3] = [SaveFormat::Bincode, SaveFormat::Json, SaveFormat::Ron];
+
+    #[test]
+    fn versions_round_trip() {
+        let data = vec![(1u32, "one".to_string()), (2, "two".to_string())];
+        for format in FORMATS {
+            let bytes = format.encode_versioned(7, &data).unwrap();
+            let (version, payload) = format.split_version(&bytes).unwrap();
+            assert_eq!(version, 7, "{:?}", format);
+            let decoded: Vec<(u32, String)> = format.decode(&payload).unwrap();
+            assert_eq!(decoded, data, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn unversioned_saves_are_version_0() {
+        let data = vec![1u32, 2, 3];
+        for format in FORMATS {
+            let bytes = format.encode(&data).unwrap();
+            let (version, payload) = format.split_version(&bytes).unwrap();
+            assert_eq!(version, 0, "{:?}", format);
+            assert_eq!(format.decode::<Vec<u32>>(&payload).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn text_saves_stay_valid() {
+        let data = vec![1u8, 2];
+        let json = SaveFormat::Json.encode_versioned(3, &data).unwrap();
+        let value: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["version"], 3);
+        assert_eq!(value["data"], serde_json::json!([1, 2]));
+
+        let ron = SaveFormat::Ron.encode_versioned(3, &data).unwrap();
+        assert!(ron.starts_with(b"// MJE save version 3\n"));
+        let decoded: Vec<u8> = ron::de::from_bytes(&ron).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn truncated_binary_header_is_corrupt() {
+        let result = SaveFormat::Bincode.split_version(b"MJE\0\x01");
+        assert!(matches!(result, Err(SaveError::Corrupt(_))));
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::VecDeque;
+
+use crate::{Console, Game, Transition};
+
+/// A screen of a game, such as a title screen, gameplay, a pause menu or a
+/// game over screen.
+///
+/// Scenes are kept on a stack, and changed with [`Console::scenes`]. Only the
+/// scene on top of the stack is ticked, after the game's own
+/// [`Game::tick`], but the scenes underneath it are still drawn if it
+/// [`Scene::is_overlay`]. Anything shared between scenes, such as the score,
+/// can be kept in the game.
+///
+/// # Example
+///
+/// ```ignore
+/// struct Paused;
+///
+/// impl Scene<MyGame> for Paused {
+///     fn tick(&mut self, game: &mut MyGame, dt: f32, console: &mut Console<MyGame>) {
+///         if console.input.key_pressed(VirtualKeyCode::Escape) {
+///             console.scenes.pop();
+///         }
+///     }
+///
+///     fn draw(&self, game: &MyGame, console: &mut Console<MyGame>) {
+///         console.graphics.draw_text("PAUSED", Vec2::new(85, 70), 0xFFFFFF);
+///     }
+///
+///     fn is_overlay(&self) -> bool {
+///         true
+///     }
+/// }
+/// ```
+pub trait Scene<G: Game> {
+    /// Called once, when the scene is added to the stack.
+    fn init(&mut self, _game: &mut G, _console: &mut Console<G>) {}
+
+    /// Called whenever the scene comes to the top of the stack, either because
+    /// it was added or because the scene above it was popped.
+    fn on_enter(&mut self, _game: &mut G, _console: &mut Console<G>) {}
+
+    /// Called whenever the scene stops being on top of the stack, either
+    /// because it was removed or because another scene was pushed on top.
+    fn on_exit(&mut self, _game: &mut G, _console: &mut Console<G>) {}
+
+    fn tick(&mut self, game: &mut G, dt: f32, console: &mut Console<G>);
+
+    /// Draw the scene. This is called every tick after the scenes have been
+    /// ticked, including for scenes that are only being drawn underneath an
+    /// overlay.
+    fn draw(&self, _game: &G, _console: &mut Console<G>) {}
+
+    /// Whether the scenes underneath this one should still be drawn, such as
+    /// for a pause menu drawn over the gameplay.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+enum ChangeKind<G: Game> {
+    Push(Box<dyn Scene<G>>),
+    Pop,
+    Replace(Box<dyn Scene<G>>),
+}
+
+struct Change<G: Game> {
+    kind: ChangeKind<G>,
+    /// The effect shown during the change and how long it takes, in seconds,
+    /// if it isn't immediate.
+    transition: Option<(Transition, f32)>,
+}
+
+/// Changes to the scene stack, requested from the [`Console`]. Changes are made
+/// once the current tick has finished.
+///
+/// By default, a change happens immediately. Changes can instead be made over
+/// time with [`Scenes::transition`] or [`Scenes::transition_with`]: neither
+/// scene is ticked while the transition plays, and the change is made halfway
+/// through, when the screen is covered.
+///
+/// A change with a transition that's requested while another transition is
+/// playing waits for it to finish, and so does every change requested after
+/// it, so that each transition plays in full and changes happen in order.
+/// Otherwise, changes without a transition still happen immediately, so a
+/// scene can push another as it's entered.
+///
+/// # Example
+///
+/// ```ignore
+/// // Fade from the title screen into the game over half a second
+/// console.scenes.transition(0.5).replace(Playing::default());
+/// // Close an iris on the game over screen
+/// console.scenes
+///     .transition_with(Transition::Iris(Color::BLACK), 1.0)
+///     .push(GameOver);
+/// ```
+pub struct Scenes<G: Game> {
+    changes: Vec<Change<G>>,
+    transition: Option<(Transition, f32)>,
+}
+
+impl<G: Game> Default for Scenes<G> {
+    fn default() -> Self {
+        Self {
+            changes: Vec::new(),
+            transition: None,
+        }
+    }
+}
+
+impl<G: Game> Scenes<G> {
+    /// Push a scene on top of the stack.
+    pub fn push(&mut self, scene: impl Scene<G> + 'static) {
+        self.change(ChangeKind::Push(Box::new(scene)));
+    }
+
+    /// Remove the scene on top of the stack.
+    pub fn pop(&mut self) {
+        self.change(ChangeKind::Pop);
+    }
+
+    /// Replace the scene on top of the stack, or push it if the stack is
+    /// empty.
+    pub fn replace(&mut self, scene: impl Scene<G> + 'static) {
+        self.change(ChangeKind::Replace(Box::new(scene)));
+    }
+
+    /// Make the next change over `duration` seconds, fading through black,
+    /// rather than immediately.
+    pub fn transition(&mut self, duration: f32) -> &mut Self {
+        self.transition_with(Transition::default(), duration)
+    }
+
+    /// Make the next change over `duration` seconds, with the given effect,
+    /// rather than immediately.
+    pub fn transition_with(&mut self, transition: Transition, duration: f32) -> &mut Self {
+        self.transition = Some((transition, duration.max(0.0)));
+        self
+    }
+
+    fn change(&mut self, kind: ChangeKind<G>) {
+        self.changes.push(Change {
+            kind,
+            transition: self.transition.take(),
+        });
+    }
+}
+
+/// A change that is being made over time.
+struct InProgress<G: Game> {
+    /// The change, until it's made halfway through.
+    change: Option<ChangeKind<G>>,
+    effect: Transition,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl<G: Game> InProgress<G> {
+    /// How much of the screen the effect covers, rising to 1 halfway through
+    /// and falling back to 0 by the end.
+    fn coverage(&self) -> f32 {
+        match self.duration > 0.0 {
+            true => 1.0 - (1.0 - 2.0 * self.elapsed / self.duration).abs(),
+            false => 1.0,
+        }
+    }
+}
+
+/// The scenes a game is made of, run by the engine after the game's tick.
+pub(crate) struct SceneStack<G: Game> {
+    stack: Vec<Box<dyn Scene<G>>>,
+    transition: Option<InProgress<G>>,
+    /// Changes waiting for the transition in progress to finish.
+    queued: VecDeque<Change<G>>,
+}
+
+impl<G: Game> Default for SceneStack<G> {
+    fn default() -> Self {
+        Self {
+            stack: Vec::new(),
+            transition: None,
+            queued: VecDeque::new(),
+        }
+    }
+}
+
+impl<G: Game> SceneStack<G> {
+    /// Tick the top scene, make any changes that have been requested, then
+    /// draw the visible scenes.
+    pub(crate) fn tick(&mut self, game: &mut G, dt: f32, console: &mut Console<G>) {
+        match &mut self.transition {
+            Some(transition) => {
+                transition.elapsed += dt;
+                let change = match transition.elapsed >= transition.duration / 2.0 {
+                    true => transition.change.take(),
+                    false => None,
+                };
+                if transition.elapsed >= transition.duration {
+                    self.transition = None;
+                }
+                if let Some(change) = change {
+                    self.apply(change, game, console);
+                }
+                while self.transition.is_none() {
+                    match self.queued.pop_front() {
+                        Some(change) => self.request(change, game, console),
+                        None => break,
+                    }
+                }
+            }
+            None => {
+                if let Some(scene) = self.stack.last_mut() {
+                    scene.tick(game, dt, console);
+                }
+            }
+        }
+
+        // Scenes can request more changes as they're entered, so keep going
+        // until they settle down
+        while !console.scenes.changes.is_empty() {
+            for change in std::mem::take(&mut console.scenes.changes) {
+                self.request(change, game, console);
+            }
+        }
+
+        let bottom = self
+            .stack
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in &self.stack[bottom..] {
+            scene.draw(game, console);
+        }
+        if let Some(transition) = &self.transition {
+            transition
+                .effect
+                .draw(&mut console.graphics, transition.coverage());
+        }
+    }
+
+    /// Start making a change, or queue it if it has to wait for the
+    /// transition in progress.
+    fn request(&mut self, change: Change<G>, game: &mut G, console: &mut Console<G>) {
+        if self.transition.is_some() && (change.transition.is_some() || !self.queued.is_empty()) {
+            self.queued.push_back(change);
+            return;
+        }
+        match change.transition {
+            Some((effect, duration)) => {
+                self.transition = Some(InProgress {
+                    change: Some(change.kind),
+                    effect,
+                    duration,
+                    elapsed: 0.0,
+                })
+            }
+            None => self.apply(change.kind, game, console),
+        }
+    }
+
+    fn apply(&mut self, change: ChangeKind<G>, game: &mut G, console: &mut Console<G>) {
+        if let Some(scene) = self.stack.last_mut() {
+            scene.on_exit(game, console);
+        }
+        match change {
+            ChangeKind::Push(scene) => self.add(scene, game, console),
+            ChangeKind::Pop => {
+                self.stack.pop();
+                if let Some(scene) = self.stack.last_mut() {
+                    scene.on_enter(game, console);
+                }
+            }
+            ChangeKind::Replace(scene) => {
+                self.stack.pop();
+                self.add(scene, game, console);
+            }
+        }
+    }
+
+    fn add(&mut self, mut scene: Box<dyn Scene<G>>, game: &mut G, console: &mut Console<G>) {
+        scene.init(game, console);
+        scene.on_enter(game, console);
+        self.stack.push(scene);
+    }
+}
@@ -0,0 +1,91 @@
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use winit::event::VirtualKeyCode;
+
+use crate::{storage, SaveError};
+
+const SETTINGS_PATH: &str = "settings.json";
+
+/// Player preferences that aren't part of a game's progress, such as the
+/// window scale and key bindings.
+///
+/// Settings are loaded by the engine before the game starts, and are stored
+/// separately from [`crate::Save`] so that wiping a save doesn't reset them.
+/// Games can store their own preferences alongside the engine's with
+/// [`Settings::get`] and [`Settings::set`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// How many screen pixels each game pixel covers, before accounting for
+    /// the monitor's scale factor. Read when the window is created.
+    pub window_scale: f32,
+    /// Whether the game starts fullscreen.
+    pub fullscreen: bool,
+    /// The master audio volume, between 0 and 1.
+    pub volume: f32,
+    /// Whether the game's [`crate::Filters`] are shown. Toggled with the
+    /// `"filters"` key binding, which defaults to F9.
+    pub filters: bool,
+    /// Keys that have been rebound by the player, by action name.
+    pub bindings: BTreeMap<String, VirtualKeyCode>,
+    /// Settings added by the game.
+    custom: BTreeMap<String, Value>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            window_scale: 4.0,
+            fullscreen: false,
+            volume: 1.0,
+            filters: true,
+            bindings: BTreeMap::new(),
+            custom: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Load the settings, falling back to the defaults if they haven't been
+    /// saved yet or couldn't be read.
+    pub(crate) fn load() -> Self {
+        storage::load(SETTINGS_PATH)
+            .and_then(|bytes| serde_json::from_slice(&bytes).map_err(SaveError::corrupt))
+            .unwrap_or_default()
+    }
+
+    /// Persist the settings, so that they are used the next time the game
+    /// starts.
+    pub fn save(&self) -> Result<(), SaveError> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(SaveError::corrupt)?;
+        storage::store(SETTINGS_PATH, &bytes)
+    }
+
+    /// The key bound to an action, or `default` if the player hasn't rebound
+    /// it.
+    pub fn key(&self, action: &str, default: VirtualKeyCode) -> VirtualKeyCode {
+        self.bindings.get(action).copied().unwrap_or(default)
+    }
+
+    /// Bind an action to a key.
+    pub fn bind(&mut self, action: impl Into<String>, key: VirtualKeyCode) {
+        self.bindings.insert(action.into(), key);
+    }
+
+    /// Get a setting added by the game. Returns `None` if the setting hasn't
+    /// been set, or was stored as a different type.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.custom
+            .get(key)
+            .and_then(|value| T::deserialize(value).ok())
+    }
+
+    /// Set a setting added by the game.
+    pub fn set<T: serde::Serialize>(&mut self, key: impl Into<String>, value: T) {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.custom.insert(key.into(), value);
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::VecDeque;
+use winit::event::VirtualKeyCode;
+use winit_input_helper::WinitInputHelper;
+
+use crate::{Rng, SaveError, Settings};
+
+/// How a game is encoded into a snapshot and decoded back out again, with its
+/// snapshot settings.
+struct Config<G> {
+    interval: usize,
+    capacity: usize,
+    encode: fn(&G) -> Result<Vec<u8>, SaveError>,
+    decode: fn(&[u8]) -> Result<G, SaveError>,
+}
+
+/// The state of the game, and everything else needed to carry on from where it
+/// was taken.
+struct Snapshot {
+    tick: usize,
+    /// How many ticks of the replay being recorded came before the snapshot.
+    recorded: usize,
+    state: Vec<u8>,
+    rng: Rng,
+    framebuffer: Vec<u32>,
+}
+
+/// Snapshots of the game's state, taken every few ticks and kept in a ring
+/// buffer, for debugging and for rewind mechanics.
+///
+/// Snapshots are opt-in, as the game's state must be serializable. Once they
+/// have been enabled with [`Snapshots::enable`], in debug builds with the
+/// `debug_tools` feature a developer can scrub through them with the following
+/// key bindings:
+///
+/// - `"snapshot_back"` (F1) pauses the game and shows the previous snapshot.
+/// - `"snapshot_forward"` (F2) shows the next snapshot.
+///
+/// Resuming or stepping the game carries on from the snapshot being shown,
+/// and forgets the snapshots that came after it.
+///
+/// # Example
+///
+/// ```ignore
+/// fn init(console: &mut Console<Self>) -> Self {
+///     // Take a snapshot every 10 ticks, and keep the last 60
+///     console.snapshots.enable(10, 60);
+///     ...
+/// }
+/// ```
+pub struct Snapshots<G> {
+    config: Option<Config<G>>,
+    snapshots: VecDeque<Snapshot>,
+    /// The snapshot being shown while a developer scrubs through them.
+    selected: Option<usize>,
+    ticks_since_last: usize,
+    /// The snapshot most recently carried on from, for the engine to rewind
+    /// its own tick count to.
+    restored: Option<Restored>,
+}
+
+/// A snapshot the game has carried on from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Restored {
+    pub(crate) tick: usize,
+    /// How many ticks of the replay being recorded to keep, if a developer
+    /// scrubbed back to the snapshot and resumed, so that what came after it
+    /// never happened. A game rewinding itself is part of what happened, and
+    /// does the same again when the replay is played back, so this is `None`.
+    pub(crate) truncate_replay: Option<usize>,
+}
+
+impl<G> Default for Snapshots<G> {
+    fn default() -> Self {
+        Self {
+            config: None,
+            snapshots: VecDeque::new(),
+            selected: None,
+            ticks_since_last: 0,
+            restored: None,
+        }
+    }
+}
+
+impl<G> Snapshots<G> {
+    /// Start taking a snapshot every `interval` ticks, keeping up to
+    /// `capacity` of them. The oldest snapshots are forgotten to make room for
+    /// new ones.
+    pub fn enable(&mut self, interval: usize, capacity: usize)
+    where
+        G: Serialize + DeserializeOwned,
+    {
+        self.config = Some(Config {
+            interval: interval.max(1),
+            capacity: capacity.max(1),
+            encode: |game| bincode::serialize(game).map_err(SaveError::corrupt),
+            decode: |bytes| bincode::deserialize(bytes).map_err(SaveError::corrupt),
+        });
+        while self.snapshots.len() > capacity.max(1) {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Stop taking snapshots, and forget the ones that have been taken.
+    pub fn disable(&mut self) {
+        self.config = None;
+        self.clear();
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// The number of snapshots being kept.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Forget the snapshots that have been taken.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.selected = None;
+    }
+
+    /// Restore the game and random number generator to the latest snapshot,
+    /// and forget it, so that rewinding again goes further back. Returns the
+    /// tick the snapshot was taken on, or `None` if there are no snapshots
+    /// left. Once the current tick has finished, [`crate::Console::tick`]
+    /// carries on from the snapshot's tick too.
+    pub fn rewind(&mut self, game: &mut G, rng: &mut Rng) -> Result<Option<usize>, SaveError> {
+        let config = match &self.config {
+            Some(config) => config,
+            None => return Ok(None),
+        };
+        match self.snapshots.pop_back() {
+            Some(snapshot) => {
+                *game = (config.decode)(&snapshot.state)?;
+                *rng = snapshot.rng;
+                self.ticks_since_last = 0;
+                self.restored = Some(Restored {
+                    tick: snapshot.tick,
+                    truncate_replay: None,
+                });
+                Ok(Some(snapshot.tick))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Take a snapshot if one is due on this tick. `recorded` is how many
+    /// ticks of the replay being recorded, if any, came before it.
+    pub(crate) fn record(
+        &mut self,
+        game: &G,
+        rng: &Rng,
+        framebuffer: &[u32],
+        tick: usize,
+        recorded: usize,
+    ) -> Result<(), String> {
+        let config = match &self.config {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+        self.ticks_since_last += 1;
+        if self.ticks_since_last < config.interval {
+            return Ok(());
+        }
+        self.ticks_since_last = 0;
+
+        let state =
+            (config.encode)(game).map_err(|err| format!("Failed to take a snapshot: {}", err))?;
+
+        if self.snapshots.len() == config.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot {
+            tick,
+            recorded,
+            state,
+            rng: rng.clone(),
+            framebuffer: framebuffer.to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Scrub through the snapshots, restoring whichever one is selected.
+    /// Returns `Ok(true)` if a snapshot was restored, in which case the game
+    /// should be paused so that it can be looked at.
+    pub(crate) fn handle_input(
+        &mut self,
+        input: &WinitInputHelper,
+        settings: &Settings,
+        game: &mut G,
+        rng: &mut Rng,
+        framebuffer: &mut [u32],
+    ) -> Result<bool, String> {
+        // This is a development tool, so players shouldn't stumble on it
+        if !cfg!(all(feature = "debug_tools", debug_assertions)) || self.snapshots.is_empty() {
+            return Ok(false);
+        }
+
+        let last = self.snapshots.len() - 1;
+        let selected = if input.key_pressed(settings.key("snapshot_back", VirtualKeyCode::F1)) {
+            match self.selected {
+                Some(selected) => selected.saturating_sub(1),
+                None => last,
+            }
+        } else if input.key_pressed(settings.key("snapshot_forward", VirtualKeyCode::F2)) {
+            match self.selected {
+                Some(selected) => (selected + 1).min(last),
+                None => return Ok(false),
+            }
+        } else {
+            return Ok(false);
+        };
+
+        let (config, snapshot) = match (&self.config, self.snapshots.get(selected)) {
+            (Some(config), Some(snapshot)) => (config, snapshot),
+            _ => return Ok(false),
+        };
+        let state = (config.decode)(&snapshot.state)
+            .map_err(|err| format!("Failed to restore a snapshot: {}", err))?;
+        *game = state;
+        *rng = snapshot.rng.clone();
+        framebuffer.copy_from_slice(&snapshot.framebuffer);
+        self.selected = Some(selected);
+        Ok(true)
+    }
+
+    /// Carry on from the snapshot being shown, if any, forgetting the ones
+    /// that came after it.
+    pub(crate) fn resume(&mut self) {
+        if let Some(selected) = self.selected.take() {
+            self.snapshots.truncate(selected + 1);
+            self.ticks_since_last = 0;
+            self.restored = self.snapshots.get(selected).map(|snapshot| Restored {
+                tick: snapshot.tick,
+                truncate_replay: Some(snapshot.recorded),
+            });
+        }
+    }
+
+    /// The snapshot the game has carried on from since this was last called,
+    /// if any.
+    pub(crate) fn take_restored(&mut self) -> Option<Restored> {
+        self.restored.take()
+    }
+
+    /// A short description of the snapshot being shown, if any.
+    pub(crate) fn label(&self) -> Option<String> {
+        let selected = self.selected?;
+        Some(format!(
+            "SNAPSHOT {}/{} TICK {}",
+            selected + 1,
+            self.snapshots.len(),
+            self.snapshots[selected].tick
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewind_restores_the_tick() {
+        let mut snapshots = Snapshots::<u32>::default();
+        snapshots.enable(2, 10);
+        let mut rng = Rng::new(1);
+        for tick in 1..=5 {
+            snapshots
+                .record(&(tick as u32), &rng, &[], tick, tick)
+                .unwrap();
+        }
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots.take_restored(), None);
+
+        let mut game = 5;
+        assert_eq!(snapshots.rewind(&mut game, &mut rng).unwrap(), Some(4));
+        assert_eq!(game, 4);
+        assert_eq!(
+            snapshots.take_restored(),
+            Some(Restored {
+                tick: 4,
+                truncate_replay: None
+            })
+        );
+        assert_eq!(snapshots.take_restored(), None);
+
+        // The next snapshot is taken a whole interval after the rewind
+        snapshots.record(&5, &rng, &[], 5, 6).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        snapshots.record(&6, &rng, &[], 6, 7).unwrap();
+        assert_eq!(snapshots.len(), 2);
+    }
+
+    #[test]
+    fn resume_forgets_later_snapshots() {
+        let mut snapshots = Snapshots::<u32>::default();
+        snapshots.enable(1, 10);
+        let rng = Rng::new(1);
+        for tick in 1..=5 {
+            snapshots
+                .record(&(tick as u32), &rng, &[], tick, tick)
+                .unwrap();
+        }
+        snapshots.selected = Some(1);
+        snapshots.resume();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(
+            snapshots.take_restored(),
+            Some(Restored {
+                tick: 2,
+                truncate_replay: Some(2)
+            })
+        );
+    }
+
+    #[test]
+    fn rewind_without_snapshots() {
+        let mut snapshots = Snapshots::<u32>::default();
+        let (mut game, mut rng) = (1, Rng::new(1));
+        assert_eq!(snapshots.rewind(&mut game, &mut rng).unwrap(), None);
+        snapshots.enable(1, 1);
+        assert_eq!(snapshots.rewind(&mut game, &mut rng).unwrap(), None);
+        assert_eq!(snapshots.take_restored(), None);
+    }
+}
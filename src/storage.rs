@@ -0,0 +1,26 @@
+//! The backend that saves and settings are persisted through.
+
+use crate::SaveError;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn load(name: &str) -> Result<Vec<u8>, SaveError> {
+    std::fs::read(name).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => SaveError::Missing,
+        _ => SaveError::Io(e.to_string()),
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn store(name: &str, bytes: &[u8]) -> Result<(), SaveError> {
+    std::fs::write(name, bytes).map_err(|e| SaveError::Io(e.to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn load(_name: &str) -> Result<Vec<u8>, SaveError> {
+    Err(SaveError::Missing)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn store(_name: &str, _bytes: &[u8]) -> Result<(), SaveError> {
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use winit::event::VirtualKeyCode;
+use winit_input_helper::WinitInputHelper;
+
+use crate::Settings;
+
+/// The speeds that the game can be slowed down or sped up to.
+const SCALES: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const NORMAL_SCALE: usize = 2;
+
+/// Lets a developer pause the game, step through it one tick at a time, and
+/// play it in slow motion or fast forward.
+///
+/// In debug builds with the `debug_tools` feature, these are controlled with
+/// the following key bindings:
+///
+/// - `"pause"` (F5) pauses and resumes the game.
+/// - `"step"` (F6) runs a single tick while paused.
+/// - `"slower"` (F7) and `"faster"` (F8) change the speed between 0.25x and
+///   4x.
+///
+/// The speed can also be set to anything with the developer console's
+/// `timescale` command.
+pub(crate) struct TimeControl {
+    paused: bool,
+    step: bool,
+    scale: f32,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            step: false,
+            scale: SCALES[NORMAL_SCALE],
+        }
+    }
+}
+
+impl TimeControl {
+    pub(crate) fn handle_input(&mut self, input: &WinitInputHelper, settings: &Settings) {
+        // These are development tools, so players shouldn't stumble on them
+        if !cfg!(all(feature = "debug_tools", debug_assertions)) {
+            return;
+        }
+
+        if input.key_pressed(settings.key("pause", VirtualKeyCode::F5)) {
+            self.paused = !self.paused;
+        }
+        if input.key_pressed(settings.key("step", VirtualKeyCode::F6)) {
+            self.paused = true;
+            self.step = true;
+        }
+
+        // Move to the next speed in the list, from wherever the scale was set
+        if input.key_pressed(settings.key("slower", VirtualKeyCode::F7)) {
+            if let Some(scale) = SCALES.iter().rev().find(|scale| **scale < self.scale) {
+                self.scale = *scale;
+            }
+        }
+        if input.key_pressed(settings.key("faster", VirtualKeyCode::F8)) {
+            if let Some(scale) = SCALES.iter().find(|scale| **scale > self.scale) {
+                self.scale = *scale;
+            }
+        }
+    }
+
+    /// Whether the game should be ticked this frame, and if so, how much to
+    /// scale the time step by.
+    pub(crate) fn advance(&mut self) -> Option<f32> {
+        if !self.paused {
+            Some(self.scale)
+        } else if self.step {
+            self.step = false;
+            Some(self.scale)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub(crate) fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub(crate) fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.0);
+    }
+
+    /// A short description of the current state, if it's anything other than
+    /// running at normal speed.
+    pub(crate) fn label(&self) -> Option<String> {
+        match (self.paused, self.scale) {
+            (true, _) => Some("PAUSED".to_string()),
+            (false, scale) if scale != 1.0 => Some(format!("X{}", scale)),
+            _ => None,
+        }
+    }
+}
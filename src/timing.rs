@@ -0,0 +1,133 @@
+use std::{collections::VecDeque, time::Duration};
+use winit::event_loop::ControlFlow;
+
+/// The number of frames that the frame time statistics are taken over.
+const HISTORY: usize = 100;
+
+/// How often the engine ticks the game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FramePacing {
+    /// Tick at a fixed number of frames per second, sleeping in between.
+    Fixed(f32),
+    /// Tick once per refresh of the display. On the web, this is driven by
+    /// the browser's `requestAnimationFrame`; natively, the monitor's refresh
+    /// rate is used as a fixed rate.
+    Display,
+    /// Tick as fast as possible, without sleeping. Useful for benchmarking,
+    /// and set with the `--uncapped` command line argument.
+    Uncapped,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        Self::Fixed(60.0)
+    }
+}
+
+/// How often the game is ticked, and how long recent frames have taken.
+///
+/// The pacing starts as [`crate::Game::FRAME_PACING`], and can be changed
+/// while the game is running with [`Timing::set_pacing`].
+pub struct Timing {
+    pacing: FramePacing,
+    /// The monitor's refresh rate, for [`FramePacing::Display`] natively.
+    pub(crate) refresh_rate: f32,
+    next_frame: instant::Instant,
+    frame_times: VecDeque<f32>,
+    /// How long the last frame spent running the game's tick, upscaling the
+    /// framebuffer and presenting it to the window, in seconds.
+    pub(crate) tick: f32,
+    pub(crate) upscale: f32,
+    pub(crate) blit: f32,
+}
+
+impl Timing {
+    pub(crate) fn new(pacing: FramePacing) -> Self {
+        Self {
+            pacing,
+            refresh_rate: 60.0,
+            next_frame: instant::Instant::now(),
+            frame_times: VecDeque::new(),
+            tick: 0.0,
+            upscale: 0.0,
+            blit: 0.0,
+        }
+    }
+
+    pub fn pacing(&self) -> FramePacing {
+        self.pacing
+    }
+
+    pub fn set_pacing(&mut self, pacing: FramePacing) {
+        self.pacing = pacing;
+    }
+
+    /// The average frames per second over the recent frames.
+    pub fn fps(&self) -> f32 {
+        let total: f32 = self.frame_times.iter().sum();
+        match total > 0.0 {
+            true => self.frame_times.len() as f32 / total,
+            false => 0.0,
+        }
+    }
+
+    /// How long the last frame took, in seconds. This is real time, unlike
+    /// the time step given to the game's tick, which can be scaled.
+    pub fn frame_time(&self) -> f32 {
+        self.frame_times.back().copied().unwrap_or(0.0)
+    }
+
+    /// The longest any of the recent frames took, in seconds.
+    pub fn max_frame_time(&self) -> f32 {
+        self.frame_times.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// How long the recent frames took, in seconds, oldest first.
+    pub fn frame_times(&self) -> impl Iterator<Item = f32> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    /// How long the game's tick took last frame, in seconds.
+    pub fn tick_time(&self) -> f32 {
+        self.tick
+    }
+
+    pub(crate) fn record_frame(&mut self, frame_time: f32) {
+        if self.frame_times.len() == HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    /// The time between frames, if they're paced by the engine.
+    fn period(&self) -> Option<f32> {
+        match self.pacing {
+            FramePacing::Fixed(fps) => Some(fps),
+            FramePacing::Display if !cfg!(target_arch = "wasm32") => Some(self.refresh_rate),
+            _ => None,
+        }
+        .map(|fps| 1.0 / fps.max(1.0))
+    }
+
+    /// Whether it's time to tick the game again.
+    pub(crate) fn is_due(&self, now: instant::Instant) -> bool {
+        self.period().is_none() || now >= self.next_frame
+    }
+
+    /// Schedule the next frame, after ticking the game.
+    pub(crate) fn frame_started(&mut self, now: instant::Instant) {
+        if let Some(period) = self.period() {
+            // If the game has fallen behind, don't try to catch up
+            self.next_frame = (self.next_frame + Duration::from_secs_f32(period)).max(now);
+        }
+    }
+
+    /// How the event loop should wait for the next frame.
+    pub(crate) fn control_flow(&self) -> ControlFlow {
+        match self.period() {
+            Some(_) => ControlFlow::WaitUntil(self.next_frame),
+            // On the web, polling waits for the next animation frame
+            None => ControlFlow::Poll,
+        }
+    }
+}
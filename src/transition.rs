@@ -0,0 +1,205 @@
+use vek::Vec2;
+
+use crate::{
+    graphics::{BlendMode, Graphics},
+    Color,
+};
+
+/// The largest block size, in pixels, that [`Transition::Pixelate`] reaches.
+const MAX_PIXEL_SIZE: f32 = 16.0;
+
+/// A 4x4 ordered dithering matrix, used by [`Transition::Dissolve`].
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// A full-screen effect for moving between scenes.
+///
+/// Transitions are drawn over the framebuffer with a progress between 0 and
+/// 1: at 0 the screen is untouched, and at 1 it's completely covered. Scene
+/// changes made with [`crate::Scenes::transition_with`] cover the screen over
+/// the first half of the transition, then uncover it over the second half.
+/// They can also be drawn by hand with [`Transition::draw`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// Fade to a colour. While drawing palette indices, each pixel steps
+    /// through whichever colours in the palette are nearest.
+    Fade(Color),
+    /// Cover the screen with a colour in a dithered pattern.
+    Dissolve(Color),
+    /// Wipe a colour across the screen from left to right.
+    WipeHorizontal(Color),
+    /// Wipe a colour down the screen from top to bottom.
+    WipeVertical(Color),
+    /// Close a circle on the centre of the screen, covering everything outside
+    /// it with a colour.
+    Iris(Color),
+    /// Break the screen up into larger and larger pixels.
+    Pixelate,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self::Fade(Color::BLACK)
+    }
+}
+
+impl Transition {
+    /// Draw the transition over everything that has been drawn so far, inside
+    /// the clip rectangle if one has been pushed.
+    pub fn draw(&self, graphics: &mut Graphics, progress: f32) {
+        let progress = progress.clamp(0.0, 1.0);
+        let area = graphics.drawable();
+        if area.w <= 0 || area.h <= 0 {
+            return;
+        }
+        let (w, h) = (area.w as usize, area.h as usize);
+        // Transitions are worked out across the area, from its top-left corner
+        let pos = |x: usize, y: usize| Vec2::new(area.x + x as i64, area.y + y as i64);
+        let pixels = (0..h).flat_map(move |y| (0..w).map(move |x| (x, y)));
+
+        match *self {
+            Self::Fade(color) => {
+                let amount = color.a as f32 / 255.0 * progress;
+                // Palette indices can't be mixed, so mix the colours they
+                // stand for and draw whichever index is nearest
+                let faded = graphics.palette().map(|palette| {
+                    let target = palette.get(color.b);
+                    (0..=255)
+                        .map(|index| palette.nearest(palette.get(index).lerp(target, amount)))
+                        .collect::<Vec<_>>()
+                });
+                match faded {
+                    Some(faded) => {
+                        for (x, y) in pixels {
+                            if let Some(index) = graphics.get(pos(x, y)) {
+                                let color = Color::index(faded[index.b as usize]);
+                                let color = color.with_alpha(index.a);
+                                graphics.blend_pixel(pos(x, y), color, BlendMode::Replace);
+                            }
+                        }
+                    }
+                    None => {
+                        let color = color.with_alpha((amount * 255.0).round() as u8);
+                        for (x, y) in pixels {
+                            graphics.draw_pixel(pos(x, y), color);
+                        }
+                    }
+                }
+            }
+            Self::Dissolve(color) => {
+                let threshold = (progress * 16.0) as u8;
+                for (x, y) in pixels.filter(|(x, y)| BAYER[y % 4][x % 4] < threshold) {
+                    graphics.draw_pixel(pos(x, y), color);
+                }
+            }
+            Self::WipeHorizontal(color) => {
+                let edge = (progress * w as f32).round() as usize;
+                for (x, y) in pixels.filter(|(x, _)| *x < edge) {
+                    graphics.draw_pixel(pos(x, y), color);
+                }
+            }
+            Self::WipeVertical(color) => {
+                let edge = (progress * h as f32).round() as usize;
+                for (x, y) in pixels.filter(|(_, y)| *y < edge) {
+                    graphics.draw_pixel(pos(x, y), color);
+                }
+            }
+            Self::Iris(color) => {
+                let centre = (w as f32 / 2.0, h as f32 / 2.0);
+                let radius = (1.0 - progress) * (centre.0 * centre.0 + centre.1 * centre.1).sqrt();
+                let outside = |(x, y): &(usize, usize)| {
+                    let dx = *x as f32 + 0.5 - centre.0;
+                    let dy = *y as f32 + 0.5 - centre.1;
+                    dx * dx + dy * dy >= radius * radius
+                };
+                for (x, y) in pixels.filter(outside) {
+                    graphics.draw_pixel(pos(x, y), color);
+                }
+            }
+            Self::Pixelate => {
+                let size = (1.0 + progress * (MAX_PIXEL_SIZE - 1.0)) as usize;
+                if size <= 1 {
+                    return;
+                }
+                // Fill each block with the colour of the pixel at its centre
+                for by in (0..h).step_by(size) {
+                    for bx in (0..w).step_by(size) {
+                        let sample = pos((bx + size / 2).min(w - 1), (by + size / 2).min(h - 1));
+                        let color = match graphics.get(sample) {
+                            Some(color) => color,
+                            None => continue,
+                        };
+                        for y in by..(by + size).min(h) {
+                            for x in bx..(bx + size).min(w) {
+                                graphics.blend_pixel(pos(x, y), color, BlendMode::Replace);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, Palette};
+    use vek::Rect;
+
+    const TRANSITIONS: [Transition; 6] = [
+        Transition::Fade(Color::RED),
+        Transition::Dissolve(Color::RED),
+        Transition::WipeHorizontal(Color::RED),
+        Transition::WipeVertical(Color::RED),
+        Transition::Iris(Color::RED),
+        Transition::Pixelate,
+    ];
+
+    #[test]
+    fn empty_canvases() {
+        for size in [Vec2::new(0, 0), Vec2::new(0, 4), Vec2::new(4, 0)] {
+            for transition in TRANSITIONS {
+                transition.draw(&mut Canvas::new(size).graphics(), 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn fades_between_palette_colours() {
+        let mut canvas = Canvas::new(Vec2::new(1, 1));
+        let mut graphics = canvas.graphics();
+        graphics.set_palette(Some(Palette::new([
+            Color::BLACK,
+            Color::WHITE,
+            Color::rgb(128, 128, 128),
+        ])));
+        graphics.clear(Color::index(1));
+        Transition::Fade(Color::index(0)).draw(&mut graphics, 0.5);
+        assert_eq!(canvas.get(Vec2::zero()), Some(Color::index(2)));
+    }
+
+    #[test]
+    fn covers_only_the_clip() {
+        for transition in &TRANSITIONS[..5] {
+            let mut canvas = Canvas::new(Vec2::new(4, 1));
+            let mut graphics = canvas.graphics();
+            graphics.push_offset(Vec2::new(1, 0));
+            graphics.push_clip(Rect::new(1, 0, 2, 1));
+            transition.draw(&mut graphics, 1.0);
+            let row = (0..4)
+                .map(|x| canvas.get(Vec2::new(x, 0)).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                row,
+                [
+                    Color::TRANSPARENT,
+                    Color::TRANSPARENT,
+                    Color::RED,
+                    Color::RED
+                ],
+                "{:?}",
+                transition
+            );
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Enlarging the framebuffer to fill the window.
+
+use vek::Vec2;
+
+use crate::Color;
+
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
+
+/// Enlarge `src` by a whole `scale` into `dst`, centring it. Each pixel becomes
+/// a `scale` by `scale` block, and anything that doesn't fit in `dst` is cut
+/// off. Pixels of `dst` outside the image are left as they are.
+///
+/// Natively, rows are upscaled in parallel.
+pub fn upscale(
+    src: &[u32],
+    src_size: Vec2<usize>,
+    dst: &mut [u32],
+    dst_size: Vec2<usize>,
+    scale: usize,
+) {
+    // There's nothing to draw while the window is minimised
+    if dst_size.x == 0 || dst_size.y == 0 || src_size.x == 0 {
+        return;
+    }
+    let scale = scale.max(1);
+    let offset = Vec2::new(
+        dst_size.x.saturating_sub(src_size.x * scale) / 2,
+        dst_size.y.saturating_sub(src_size.y * scale) / 2,
+    );
+    // The number of source pixels in each row that fit in the window
+    let columns = src_size.x.min((dst_size.x - offset.x) / scale);
+
+    let upscale_row = |(src_row, band): (&[u32], &mut [u32])| {
+        // Fill the first row of the band, then copy it to the rest
+        let first = &mut band[offset.x..dst_size.x];
+        for (i, pixel) in src_row[..columns].iter().enumerate() {
+            first[i * scale..(i + 1) * scale].fill(*pixel);
+        }
+        for row in 1..band.len() / dst_size.x {
+            band.copy_within(0..dst_size.x, row * dst_size.x);
+        }
+    };
+
+    let dst = &mut dst[offset.y * dst_size.x..];
+    let band_len = dst_size.x * scale;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    src.par_chunks(src_size.x)
+        .zip(dst.par_chunks_mut(band_len))
+        .for_each(upscale_row);
+
+    // Threads aren't available on the web
+    #[cfg(target_arch = "wasm32")]
+    src.chunks(src_size.x)
+        .zip(dst.chunks_mut(band_len))
+        .for_each(upscale_row);
+}
+
+/// How the framebuffer is enlarged to fill the window.
+///
+/// Set with [`crate::Game::UPSCALER`], and changed while the game is running
+/// with [`crate::Console::upscaler`]. While [`crate::Filters`] are shown, they
+/// do the upscaling instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Upscaler {
+    /// Turn each pixel into a square block, at the largest whole scale that
+    /// fits in the window. Perfectly crisp, but may leave a border.
+    #[default]
+    Nearest,
+    /// Round off the corners of diagonal edges with Scale3x or Scale2x
+    /// (also known as AdvMAME3x and EPX), whichever the scale is a multiple
+    /// of, then enlarge the rest of the way as [`Upscaler::Nearest`]. Pixels
+    /// are only ever copied, so no new colours are made.
+    Epx,
+    /// Smooth edges at twice the size, in the spirit of hq2x, then enlarge
+    /// the rest of the way as [`Upscaler::Nearest`]. Like [`Upscaler::Epx`],
+    /// but colours are compared by how alike they look rather than exactly,
+    /// and corners are blended rather than copied. Only used at even scales.
+    Hq2x,
+    /// Fill as much of the window as possible, at a fractional scale. Pixels
+    /// are kept sharp, with only the edges between them blended.
+    SharpBilinear,
+}
+
+impl Upscaler {
+    /// Enlarge `src` to fit in `dst`, centring it. Pixels of `dst` outside the
+    /// image are left as they are.
+    pub fn upscale(
+        &self,
+        src: &[u32],
+        src_size: Vec2<usize>,
+        dst: &mut [u32],
+        dst_size: Vec2<usize>,
+    ) {
+        // There's nothing to draw while the window is minimised
+        if dst_size.x == 0 || dst_size.y == 0 || src_size.x == 0 || src_size.y == 0 {
+            return;
+        }
+        let scale = (dst_size.x / src_size.x)
+            .min(dst_size.y / src_size.y)
+            .max(1);
+        match (*self, scale % 3, scale % 2) {
+            (Self::Epx, 0, _) => {
+                let enlarged = scale3x(src, src_size);
+                upscale(&enlarged, src_size * 3, dst, dst_size, scale / 3)
+            }
+            (Self::Epx, _, 0) => {
+                let enlarged = double(src, src_size, epx_corner);
+                upscale(&enlarged, src_size * 2, dst, dst_size, scale / 2)
+            }
+            (Self::Hq2x, _, 0) => {
+                let enlarged = double(src, src_size, hq_corner);
+                upscale(&enlarged, src_size * 2, dst, dst_size, scale / 2)
+            }
+            (Self::SharpBilinear, _, _) => sharp_bilinear(src, src_size, dst, dst_size),
+            _ => upscale(src, src_size, dst, dst_size, scale),
+        }
+    }
+}
+
+/// Call `f` with each row of `dst` and its index, in parallel natively.
+fn for_each_row(dst: &mut [u32], width: usize, f: impl Fn((usize, &mut [u32])) + Send + Sync) {
+    #[cfg(not(target_arch = "wasm32"))]
+    dst.par_chunks_mut(width).enumerate().for_each(f);
+
+    // Threads aren't available on the web
+    #[cfg(target_arch = "wasm32")]
+    dst.chunks_mut(width).enumerate().for_each(f);
+}
+
+/// The pixel at `pos` moved by `offset`, clamped to the edges of the image.
+fn neighbour(src: &[u32], size: Vec2<usize>, pos: Vec2<usize>, offset: Vec2<isize>) -> u32 {
+    let x = (pos.x as isize + offset.x).clamp(0, size.x as isize - 1) as usize;
+    let y = (pos.y as isize + offset.y).clamp(0, size.y as isize - 1) as usize;
+    src[y * size.x + x]
+}
+
+/// Enlarge an image to twice its size, deciding the colour of each corner of
+/// each pixel with `corner`.
+fn double(src: &[u32], size: Vec2<usize>, corner: fn(u32, u32, u32, u32, u32) -> u32) -> Vec<u32> {
+    let mut dst = vec![0; size.x * size.y * 4];
+    for_each_row(&mut dst, size.x * 2, |(y, row)| {
+        for x in 0..size.x {
+            let at = |dx, dy| neighbour(src, size, Vec2::new(x, y / 2), Vec2::new(dx, dy));
+            let (p, above, below, left, right) =
+                (at(0, 0), at(0, -1), at(0, 1), at(-1, 0), at(1, 0));
+            let (near, far) = match y % 2 {
+                0 => (above, below),
+                _ => (below, above),
+            };
+            row[x * 2] = corner(p, near, left, far, right);
+            row[x * 2 + 1] = corner(p, near, right, far, left);
+        }
+    });
+    dst
+}
+
+/// The colour of a corner of pixel `p` in Scale2x, from its vertical and
+/// horizontal neighbours on that side, `v` and `h`, and on the opposite sides.
+/// The corner takes on the neighbours' colour if they meet in an edge.
+fn epx_corner(p: u32, v: u32, h: u32, v_opposite: u32, h_opposite: u32) -> u32 {
+    if v == h && v != h_opposite && h != v_opposite {
+        v
+    } else {
+        p
+    }
+}
+
+/// Like [`epx_corner`], but comparing colours by how alike they look, and
+/// blending towards the neighbours' colour rather than copying it.
+fn hq_corner(p: u32, v: u32, h: u32, v_opposite: u32, h_opposite: u32) -> u32 {
+    if alike(v, h) && !alike(v, h_opposite) && !alike(h, v_opposite) {
+        blend(p, blend(v, h, 0.5), 0.75)
+    } else {
+        p
+    }
+}
+
+/// Enlarge an image to three times its size with Scale3x.
+fn scale3x(src: &[u32], size: Vec2<usize>) -> Vec<u32> {
+    let mut dst = vec![0; size.x * size.y * 9];
+    for_each_row(&mut dst, size.x * 3, |(y, row)| {
+        for x in 0..size.x {
+            let at = |dx, dy| neighbour(src, size, Vec2::new(x, y / 3), Vec2::new(dx, dy));
+            let [a, b, c, d, e, f, g, h, i] = [
+                at(-1, -1),
+                at(0, -1),
+                at(1, -1),
+                at(-1, 0),
+                at(0, 0),
+                at(1, 0),
+                at(-1, 1),
+                at(0, 1),
+                at(1, 1),
+            ];
+            let pick = |edge: bool, color: u32| if edge { color } else { e };
+            let out = match (b != h && d != f, y % 3) {
+                (false, _) => [e, e, e],
+                (true, 0) => [
+                    pick(d == b, d),
+                    pick((d == b && e != c) || (b == f && e != a), b),
+                    pick(b == f, f),
+                ],
+                (true, 1) => [
+                    pick((d == b && e != g) || (d == h && e != a), d),
+                    e,
+                    pick((b == f && e != i) || (h == f && e != c), f),
+                ],
+                (true, _) => [
+                    pick(d == h, d),
+                    pick((d == h && e != i) || (h == f && e != g), h),
+                    pick(h == f, f),
+                ],
+            };
+            row[x * 3..x * 3 + 3].copy_from_slice(&out);
+        }
+    });
+    dst
+}
+
+/// Whether two colours look alike, by the thresholds hq2x uses.
+fn alike(a: u32, b: u32) -> bool {
+    let yuv = |pixel: u32| {
+        let color = Color::from_pixel(pixel);
+        let (r, g, b) = (color.r as f32, color.g as f32, color.b as f32);
+        [
+            0.299 * r + 0.587 * g + 0.114 * b,
+            -0.169 * r - 0.331 * g + 0.5 * b,
+            0.5 * r - 0.419 * g - 0.081 * b,
+        ]
+    };
+    let (a, b) = (yuv(a), yuv(b));
+    (a[0] - b[0]).abs() <= 48.0 && (a[1] - b[1]).abs() <= 7.0 && (a[2] - b[2]).abs() <= 6.0
+}
+
+/// Mix two colours, from all `a` when `t` is 0 to all `b` when it's 1.
+fn blend(a: u32, b: u32, t: f32) -> u32 {
+    Color::from_pixel(a)
+        .lerp(Color::from_pixel(b), t)
+        .to_pixel()
+}
+
+/// Enlarge `src` by the largest fractional scale that fits in `dst`, as if it
+/// had been enlarged by a whole scale first then smoothly resized the rest of
+/// the way, so that only the edges between pixels are blended.
+fn sharp_bilinear(src: &[u32], src_size: Vec2<usize>, dst: &mut [u32], dst_size: Vec2<usize>) {
+    let scale = (dst_size.x as f32 / src_size.x as f32).min(dst_size.y as f32 / src_size.y as f32);
+    let image = (src_size.as_::<f32>() * scale).as_::<usize>();
+    let offset = Vec2::new(
+        dst_size.x.saturating_sub(image.x) / 2,
+        dst_size.y.saturating_sub(image.y) / 2,
+    );
+    let prescale = scale.floor().max(1.0);
+    // How far from the centre of a pixel it stays solid, as a fraction of
+    // its size
+    let solid = 0.5 - 0.5 / prescale;
+
+    // The two source pixels to blend between for each column or row of the
+    // image, and how far to blend
+    let samples = |len: usize, src_len: usize| {
+        (0..len)
+            .map(|i| {
+                let pos = (i as f32 + 0.5) / scale;
+                let from_centre = pos.fract() - 0.5;
+                let pos = pos.floor() + (from_centre - from_centre.clamp(-solid, solid)) * prescale;
+                let first = pos.floor();
+                let index = |pos: f32| (pos.max(0.0) as usize).min(src_len - 1);
+                (index(first), index(first + 1.0), pos - first)
+            })
+            .collect::<Vec<_>>()
+    };
+    let columns = samples(image.x, src_size.x);
+    let rows = samples(image.y, src_size.y);
+
+    for_each_row(dst, dst_size.x, |(y, row)| {
+        let (top, bottom, t) = match rows.get(y.wrapping_sub(offset.y)) {
+            Some(&sample) => sample,
+            None => return,
+        };
+        for (x, &(left, right, s)) in columns.iter().enumerate() {
+            let at = |x: usize, y: usize| src[y * src_size.x + x];
+            row[offset.x + x] = blend(
+                blend(at(left, top), at(right, top), s),
+                blend(at(left, bottom), at(right, bottom), s),
+                t,
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPSCALERS: [Upscaler; 4] = [
+        Upscaler::Nearest,
+        Upscaler::Epx,
+        Upscaler::Hq2x,
+        Upscaler::SharpBilinear,
+    ];
+
+    #[test]
+    fn nearest_centres_blocks() {
+        let mut dst = vec![9; 6 * 4];
+        upscale(&[1, 2], Vec2::new(2, 1), &mut dst, Vec2::new(6, 4), 2);
+        #[rustfmt::skip]
+        assert_eq!(dst, [
+            9, 9, 9, 9, 9, 9,
+            9, 1, 1, 2, 2, 9,
+            9, 1, 1, 2, 2, 9,
+            9, 9, 9, 9, 9, 9,
+        ]);
+    }
+
+    #[test]
+    fn nearest_cuts_off_what_doesnt_fit() {
+        // Only whole blocks are drawn
+        let mut dst = vec![0; 3];
+        upscale(&[1, 2], Vec2::new(2, 1), &mut dst, Vec2::new(3, 1), 2);
+        assert_eq!(dst, [1, 1, 0]);
+    }
+
+    #[test]
+    fn empty_windows() {
+        let src = [1; 4];
+        for size in [Vec2::new(0, 0), Vec2::new(0, 4), Vec2::new(4, 0)] {
+            let mut dst = vec![0; size.product()];
+            upscale(&src, Vec2::new(2, 2), &mut dst, size, 2);
+        }
+        upscale(&[], Vec2::new(0, 0), &mut [0; 4], Vec2::new(2, 2), 2);
+    }
+
+    #[test]
+    fn upscalers_handle_empty_windows() {
+        let src = [1; 4];
+        for upscaler in UPSCALERS {
+            for size in [Vec2::new(0, 0), Vec2::new(0, 4), Vec2::new(4, 0)] {
+                let mut dst = vec![0; size.product()];
+                upscaler.upscale(&src, Vec2::new(2, 2), &mut dst, size);
+            }
+            upscaler.upscale(&[], Vec2::new(0, 0), &mut [0; 4], Vec2::new(2, 2));
+        }
+    }
+
+    #[test]
+    fn solid_colours_stay_solid() {
+        let src = [0x123456; 3 * 2];
+        for upscaler in UPSCALERS {
+            for scale in [1, 2, 3, 4, 6] {
+                let size = Vec2::new(3, 2) * scale;
+                let mut dst = vec![0; size.product()];
+                upscaler.upscale(&src, Vec2::new(3, 2), &mut dst, size);
+                assert!(
+                    dst.iter().all(|pixel| *pixel == 0x123456),
+                    "{:?} at {}x",
+                    upscaler,
+                    scale
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn epx_rounds_corners() {
+        // A diagonal edge between black and white
+        #[rustfmt::skip]
+        let src = [
+            0, 0,
+            0, 1,
+        ];
+        let enlarged = double(&src, Vec2::new(2, 2), epx_corner);
+        // The black pixels around the white one cut off its top-left corner,
+        // but the rest of it stays white
+        assert_eq!(enlarged[4 + 1], 0);
+        assert_eq!(enlarged[2 * 4 + 2], 0);
+        assert_eq!(enlarged[2 * 4 + 3], 1);
+        assert_eq!(enlarged[3 * 4 + 3], 1);
+    }
+
+    #[test]
+    fn sharp_bilinear_fills_fractional_scales() {
+        #[rustfmt::skip]
+        let src = [
+            0x000000, 0xFFFFFF,
+            0x000000, 0xFFFFFF,
+        ];
+        let mut dst = vec![0x0000FF; 5 * 5];
+        Upscaler::SharpBilinear.upscale(&src, Vec2::new(2, 2), &mut dst, Vec2::new(5, 5));
+        // At 2.5x, only the middle column is blended
+        for row in dst.chunks(5) {
+            assert_eq!(row, [0x000000, 0x000000, 0x808080, 0xFFFFFF, 0xFFFFFF]);
+        }
+    }
+}